@@ -0,0 +1,177 @@
+use crate::db;
+use crate::error::AppError;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Version written to backup manifests, bump on incompatible changes
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DB_ENTRY: &str = "whytchat.db";
+
+/// First entry of a backup archive, describing what it holds
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub conversations: i64,
+    pub messages: i64,
+    /// Presets used by the saved conversations, to spot missing models on import
+    pub preset_ids: Vec<String>,
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn io_error(action: &str, path: &Path, e: impl std::fmt::Display) -> AppError {
+    AppError::Io(format!("Failed to {} {}: {}", action, path.display(), e))
+}
+
+/// Write a zip with the manifest and a consistent copy of the database to `dest`
+///
+/// The copy is taken with `VACUUM INTO`, so the live connection keeps
+/// working; the archive only replaces `dest` once fully written.
+pub fn export_all(conn: &Connection, dest: &Path) -> Result<BackupManifest, AppError> {
+    let (conversations, messages) = db::count_rows(conn)?;
+    let manifest = BackupManifest {
+        version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        conversations,
+        messages,
+        preset_ids: db::conversation_preset_ids(conn)?,
+    };
+
+    let snapshot = with_suffix(dest, ".db.tmp");
+    let _ = fs::remove_file(&snapshot); // VACUUM INTO refuses an existing file
+    conn.execute("VACUUM INTO ?1", [snapshot.to_string_lossy().to_string()])?;
+    let written = write_archive(&manifest, &snapshot, &with_suffix(dest, ".part"));
+    let _ = fs::remove_file(&snapshot);
+    let part = written?;
+    fs::rename(&part, dest).map_err(|e| io_error("write", dest, e))?;
+    Ok(manifest)
+}
+
+fn write_archive(
+    manifest: &BackupManifest,
+    snapshot: &Path,
+    part: &Path,
+) -> Result<PathBuf, AppError> {
+    let file = File::create(part).map_err(|e| io_error("create", part, e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let zip_error = |e: zip::result::ZipError| io_error("write", part, e);
+
+    zip.start_file(MANIFEST_ENTRY, options).map_err(zip_error)?;
+    let json = serde_json::to_vec_pretty(manifest)?;
+    zip.write_all(&json)
+        .map_err(|e| io_error("write", part, e))?;
+
+    let mut db_file = File::open(snapshot).map_err(|e| io_error("read", snapshot, e))?;
+    let large = db_file.metadata().map(|m| m.len()).unwrap_or(0) >= u32::MAX as u64;
+    zip.start_file(DB_ENTRY, options.large_file(large))
+        .map_err(zip_error)?;
+    io::copy(&mut db_file, &mut zip).map_err(|e| io_error("write", part, e))?;
+
+    zip.finish().map_err(zip_error)?;
+    Ok(part.to_path_buf())
+}
+
+/// Check a backup archive and extract its database next to `db_path`
+///
+/// Returns the manifest and the extracted file, which `swap_database` puts
+/// in place. Nothing of the current data is touched here.
+pub fn stage_import(src: &Path, db_path: &Path) -> Result<(BackupManifest, PathBuf), AppError> {
+    let file = File::open(src).map_err(|e| io_error("open", src, e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| AppError::InvalidInput(format!("Not a backup archive: {}", e)))?;
+
+    let manifest: BackupManifest = {
+        let entry = archive
+            .by_name(MANIFEST_ENTRY)
+            .map_err(|_| AppError::InvalidInput("Backup archive has no manifest".to_string()))?;
+        serde_json::from_reader(entry)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid backup manifest: {}", e)))?
+    };
+    if manifest.version == 0 || manifest.version > FORMAT_VERSION {
+        return Err(AppError::Unsupported(format!(
+            "Unsupported backup version {} (expected at most {})",
+            manifest.version, FORMAT_VERSION
+        )));
+    }
+
+    let staged = with_suffix(db_path, ".import");
+    {
+        let mut entry = archive
+            .by_name(DB_ENTRY)
+            .map_err(|_| AppError::InvalidInput("Backup archive has no database".to_string()))?;
+        let mut out = File::create(&staged).map_err(|e| io_error("create", &staged, e))?;
+        io::copy(&mut entry, &mut out).map_err(|e| io_error("write", &staged, e))?;
+    }
+    if let Err(e) = check_database(&staged) {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+    Ok((manifest, staged))
+}
+
+/// Reject a file that isn't an intact WhytChat database
+fn check_database(path: &Path) -> Result<(), AppError> {
+    let conn = Connection::open(path)?;
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(AppError::InvalidInput(format!(
+            "Backup database is corrupted: {}",
+            integrity
+        )));
+    }
+    db::count_rows(&conn)
+        .map(|_| ())
+        .map_err(|_| AppError::InvalidInput("Backup database has no conversations".to_string()))
+}
+
+/// Replace the database file with `staged`, keeping the old one as `.bak`
+///
+/// Every connection to `db_path` must be closed first.
+pub fn swap_database(staged: &Path, db_path: &Path) -> Result<(), AppError> {
+    let backup = with_suffix(db_path, ".bak");
+    let _ = fs::remove_file(&backup);
+    if db_path.exists() {
+        fs::rename(db_path, &backup).map_err(|e| io_error("move", db_path, e))?;
+    }
+    // Leftovers of the old file would be replayed into the new one
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(with_suffix(db_path, suffix));
+    }
+    if let Err(e) = fs::rename(staged, db_path) {
+        let _ = fs::rename(&backup, db_path);
+        return Err(io_error("restore", db_path, e));
+    }
+    Ok(())
+}
+
+/// Undo `swap_database`: drop the imported file and move `.bak` back in place
+///
+/// Every connection to `db_path` must be closed first.
+pub fn restore_previous(db_path: &Path) -> Result<(), AppError> {
+    let backup = with_suffix(db_path, ".bak");
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(with_suffix(db_path, suffix));
+    }
+    if backup.exists() {
+        // Renaming over the imported file keeps one of the two in place if it fails
+        fs::rename(&backup, db_path).map_err(|e| io_error("restore", db_path, e))
+    } else {
+        let _ = fs::remove_file(db_path);
+        Ok(())
+    }
+}
@@ -1,21 +1,9 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Result};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 fn app_base_dir() -> Result<PathBuf, String> {
-    if cfg!(debug_assertions) {
-        let src_tauri = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        Ok(src_tauri
-            .parent()
-            .ok_or("src-tauri has no parent")?
-            .to_path_buf())
-    } else {
-        Ok(std::env::current_exe()
-            .map_err(|e| format!("Failed to get exe path: {}", e))?
-            .parent()
-            .ok_or("No parent directory for exe")?
-            .to_path_buf())
-    }
+    crate::paths::base_dir()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,6 +28,17 @@ pub struct Conversation {
     pub dataset_ids: Option<String>, // JSON array or comma-separated list of dataset IDs
     pub created_at: String,
     pub updated_at: String,
+    /// Max seconds without streamed data before generation is abandoned (None = default)
+    pub generation_timeout_secs: Option<i64>,
+    /// OpenAI-style penalties, left out of requests when unset
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub top_k: Option<i32>,
+    pub min_p: Option<f32>,
+    /// Listed before unpinned conversations
+    pub pinned: bool,
+    /// Strict (no invention) rules are added to the system prompt
+    pub strict: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,10 +48,39 @@ pub struct Message {
     pub role: String,
     pub content: String,
     pub created_at: String,
+    /// Why generation of an assistant message ended (`stop`, `length`, `timeout`, ...)
+    pub finish_reason: Option<String>,
+    /// Stitched together after the stream dropped and was reconnected
+    pub reconnected: bool,
+    /// User message this assistant answer is a variant for, `None` until regenerated
+    pub parent_message_id: Option<i64>,
+    /// 0 for the original answer, then 1, 2, ... for alternatives
+    pub variant_index: i64,
+    /// Number of variants in this message's group (1 without alternatives)
+    pub variant_count: i64,
+    /// Whether this variant is the one shown and sent as history
+    pub active: bool,
+    /// Settings an assistant message was generated with, `None` for user and older messages
+    pub generation_params: Option<GenerationParams>,
+}
+
+/// Snapshot of the request that produced an assistant message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GenerationParams {
+    pub model: String,
+    pub temperature: f32,
+    pub top_p: f32,
+    pub max_tokens: i32,
+    pub repeat_penalty: f32,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub top_k: Option<i32>,
+    pub min_p: Option<f32>,
+    /// Seed sent with the request, `None` when llama-server picked its own
+    pub seed: Option<u32>,
 }
 
 pub fn get_db_path(_app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    // Store DB inside the application folder for portability
     let mut base = app_base_dir()?;
     base.push("data");
     std::fs::create_dir_all(&base).map_err(|e| format!("Failed to create data dir: {}", e))?;
@@ -105,6 +133,28 @@ pub fn init_db(app_handle: &tauri::AppHandle) -> Result<Connection> {
 
     // Migration: Add dataset_ids column to existing tables
     let _ = conn.execute("ALTER TABLE conversations ADD COLUMN dataset_ids TEXT", []); // Ignore error if column already exists
+    let _ = conn.execute(
+        "ALTER TABLE conversations ADD COLUMN generation_timeout_secs INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE conversations ADD COLUMN presence_penalty REAL",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE conversations ADD COLUMN frequency_penalty REAL",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE conversations ADD COLUMN top_k INTEGER", []);
+    let _ = conn.execute("ALTER TABLE conversations ADD COLUMN min_p REAL", []);
+    let _ = conn.execute(
+        "ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE conversations ADD COLUMN strict INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS messages (
@@ -118,6 +168,26 @@ pub fn init_db(app_handle: &tauri::AppHandle) -> Result<Connection> {
         [],
     )?;
 
+    let _ = conn.execute("ALTER TABLE messages ADD COLUMN finish_reason TEXT", []);
+    let _ = conn.execute(
+        "ALTER TABLE messages ADD COLUMN reconnected INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    // Alternative answers: siblings share the user message they reply to
+    let _ = conn.execute(
+        "ALTER TABLE messages ADD COLUMN parent_message_id INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE messages ADD COLUMN variant_index INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE messages ADD COLUMN active INTEGER NOT NULL DEFAULT 1",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE messages ADD COLUMN generation_params TEXT", []);
+
     // Create indexes
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_conversations_group_id ON conversations(group_id)",
@@ -128,6 +198,47 @@ pub fn init_db(app_handle: &tauri::AppHandle) -> Result<Connection> {
         "CREATE INDEX IF NOT EXISTS idx_messages_conversation_id ON messages(conversation_id)",
         [],
     )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_messages_parent_message_id ON messages(parent_message_id)",
+        [],
+    )?;
+
+    // App-wide preferences, values are JSON
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+        [],
+    )?;
+
+    // Prompt-engineering dialogues, history is a JSON array of turns
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prompt_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            preset_id TEXT,
+            intent TEXT NOT NULL,
+            history TEXT NOT NULL DEFAULT '[]',
+            final_prompt TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+        [],
+    )?;
+
+    // GGUF files found by scan_models that match no pack, path relative to the models folder
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS user_presets (
+            id TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            rel_path TEXT NOT NULL UNIQUE,
+            size_bytes INTEGER,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+        [],
+    )?;
     Ok(conn)
 }
 
@@ -150,34 +261,49 @@ pub fn create_group(conn: &Connection, name: &str) -> Result<i64> {
     Ok(conn.last_insert_rowid())
 }
 
-pub fn list_conversations(conn: &Connection) -> Result<Vec<Conversation>> {
-    let mut stmt = conn.prepare(
-        "SELECT c.id, c.name, c.group_id, g.name as group_name, c.preset_id,
+/// Columns read by `conversation_from_row`, in order
+const CONVERSATION_SELECT: &str =
+    "SELECT c.id, c.name, c.group_id, g.name as group_name, c.preset_id,
                 c.system_prompt, c.temperature, c.top_p, c.max_tokens, c.repeat_penalty,
-                c.dataset_ids, c.created_at, c.updated_at
+                c.dataset_ids, c.created_at, c.updated_at, c.generation_timeout_secs,
+                c.presence_penalty, c.frequency_penalty, c.top_k, c.min_p, c.pinned,
+                c.strict
          FROM conversations c
-         LEFT JOIN groups g ON c.group_id = g.id
-         ORDER BY c.updated_at DESC",
-    )?;
+         LEFT JOIN groups g ON c.group_id = g.id";
+
+fn conversation_from_row(row: &rusqlite::Row) -> Result<Conversation> {
+    Ok(Conversation {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        group_id: row.get(2)?,
+        group_name: row.get(3)?,
+        preset_id: row.get(4)?,
+        system_prompt: row.get(5)?,
+        temperature: row.get(6)?,
+        top_p: row.get(7)?,
+        max_tokens: row.get(8)?,
+        repeat_penalty: row.get(9)?,
+        dataset_ids: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        generation_timeout_secs: row.get(13)?,
+        presence_penalty: row.get(14)?,
+        frequency_penalty: row.get(15)?,
+        top_k: row.get(16)?,
+        min_p: row.get(17)?,
+        pinned: row.get(18)?,
+        strict: row.get(19)?,
+    })
+}
+
+pub fn list_conversations(conn: &Connection) -> Result<Vec<Conversation>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} ORDER BY c.pinned DESC, c.updated_at DESC",
+        CONVERSATION_SELECT
+    ))?;
 
     let conversations = stmt
-        .query_map([], |row| {
-            Ok(Conversation {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                group_id: row.get(2)?,
-                group_name: row.get(3)?,
-                preset_id: row.get(4)?,
-                system_prompt: row.get(5)?,
-                temperature: row.get(6)?,
-                top_p: row.get(7)?,
-                max_tokens: row.get(8)?,
-                repeat_penalty: row.get(9)?,
-                dataset_ids: row.get(10)?,
-                created_at: row.get(11)?,
-                updated_at: row.get(12)?,
-            })
-        })?
+        .query_map([], conversation_from_row)?
         .collect::<Result<Vec<_>>>()?;
     Ok(conversations)
 }
@@ -193,68 +319,251 @@ pub struct ConversationParams {
     pub max_tokens: i32,
     pub repeat_penalty: f32,
     pub dataset_ids: Option<String>,
+    pub generation_timeout_secs: Option<i64>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub top_k: Option<i32>,
+    pub min_p: Option<f32>,
+    pub strict: bool,
 }
 
 pub fn get_conversation(conn: &Connection, id: i64) -> Result<Conversation> {
-    let mut stmt = conn.prepare(
-        "SELECT c.id, c.name, c.group_id, g.name as group_name, c.preset_id,
-                c.system_prompt, c.temperature, c.top_p, c.max_tokens, c.repeat_penalty,
-                c.dataset_ids, c.created_at, c.updated_at
-         FROM conversations c
-         LEFT JOIN groups g ON c.group_id = g.id
-         WHERE c.id = ?1",
-    )?;
-
-    stmt.query_row([id], |row| {
-        Ok(Conversation {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            group_id: row.get(2)?,
-            group_name: row.get(3)?,
-            preset_id: row.get(4)?,
-            system_prompt: row.get(5)?,
-            temperature: row.get(6)?,
-            top_p: row.get(7)?,
-            max_tokens: row.get(8)?,
-            repeat_penalty: row.get(9)?,
-            dataset_ids: row.get(10)?,
-            created_at: row.get(11)?,
-            updated_at: row.get(12)?,
-        })
-    })
+    let mut stmt = conn.prepare(&format!("{} WHERE c.id = ?1", CONVERSATION_SELECT))?;
+    stmt.query_row([id], conversation_from_row)
 }
 
 pub fn create_conversation(conn: &Connection, params: ConversationParams) -> Result<i64> {
     conn.execute(
-        "INSERT INTO conversations (name, group_id, preset_id, system_prompt, temperature, top_p, max_tokens, repeat_penalty, dataset_ids)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        rusqlite::params![params.name, params.group_id, params.preset_id, params.system_prompt, params.temperature, params.top_p, params.max_tokens, params.repeat_penalty, params.dataset_ids],
+        "INSERT INTO conversations (name, group_id, preset_id, system_prompt, temperature, top_p, max_tokens, repeat_penalty, dataset_ids, generation_timeout_secs, presence_penalty, frequency_penalty, top_k, min_p, strict)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        rusqlite::params![params.name, params.group_id, params.preset_id, params.system_prompt, params.temperature, params.top_p, params.max_tokens, params.repeat_penalty, params.dataset_ids, params.generation_timeout_secs, params.presence_penalty, params.frequency_penalty, params.top_k, params.min_p, params.strict],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
-pub fn list_messages(conn: &Connection, conversation_id: i64) -> Result<Vec<Message>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, conversation_id, role, content, created_at
-         FROM messages
-         WHERE conversation_id = ?1
-         ORDER BY created_at ASC",
+/// Sampling parameters replaced as a whole by `update_conversation`
+#[derive(Debug)]
+pub struct ConversationParamsUpdate {
+    pub temperature: f32,
+    pub top_p: f32,
+    pub max_tokens: i32,
+    pub repeat_penalty: f32,
+    pub generation_timeout_secs: Option<i64>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub top_k: Option<i32>,
+    pub min_p: Option<f32>,
+}
+
+/// Update name, system prompt (empty clears it) and/or parameters, `None` keeps the current value
+pub fn update_conversation(
+    conn: &mut Connection,
+    id: i64,
+    name: Option<&str>,
+    system_prompt: Option<&str>,
+    params: Option<&ConversationParamsUpdate>,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    let changed = tx.execute(
+        "UPDATE conversations SET
+            name = COALESCE(?1, name),
+            system_prompt = CASE WHEN ?2 IS NULL THEN system_prompt ELSE NULLIF(?2, '') END,
+            updated_at = datetime('now')
+         WHERE id = ?3",
+        rusqlite::params![name, system_prompt, id],
+    )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    if let Some(p) = params {
+        tx.execute(
+            "UPDATE conversations SET temperature = ?1, top_p = ?2, max_tokens = ?3,
+                repeat_penalty = ?4, generation_timeout_secs = ?5,
+                presence_penalty = ?6, frequency_penalty = ?7, top_k = ?8, min_p = ?9
+             WHERE id = ?10",
+            rusqlite::params![
+                p.temperature,
+                p.top_p,
+                p.max_tokens,
+                p.repeat_penalty,
+                p.generation_timeout_secs,
+                p.presence_penalty,
+                p.frequency_penalty,
+                p.top_k,
+                p.min_p,
+                id
+            ],
+        )?;
+    }
+    tx.commit()
+}
+
+/// Pin or unpin a conversation, its `updated_at` is left alone
+pub fn set_conversation_pinned(conn: &Connection, id: i64, pinned: bool) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE conversations SET pinned = ?1 WHERE id = ?2",
+        rusqlite::params![pinned, id],
+    )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+/// Turn strict rules on or off for a conversation's next replies
+pub fn set_conversation_strict(conn: &Connection, id: i64, strict: bool) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE conversations SET strict = ?1, updated_at = datetime('now') WHERE id = ?2",
+        rusqlite::params![strict, id],
     )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn set_generation_timeout(conn: &Connection, id: i64, secs: Option<i64>) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE conversations SET generation_timeout_secs = ?1 WHERE id = ?2",
+        rusqlite::params![secs, id],
+    )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+/// Columns read by `message_from_row`, in order
+const MESSAGE_SELECT: &str =
+    "SELECT m.id, m.conversation_id, m.role, m.content, m.created_at, m.finish_reason,
+            m.reconnected, m.parent_message_id, m.variant_index, m.active,
+            m.generation_params,
+            CASE WHEN m.parent_message_id IS NULL THEN 1
+                 ELSE (SELECT COUNT(*) FROM messages v
+                       WHERE v.parent_message_id = m.parent_message_id) END
+     FROM messages m";
+
+fn message_from_row(row: &rusqlite::Row) -> Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        created_at: row.get(4)?,
+        finish_reason: row.get(5)?,
+        reconnected: row.get(6)?,
+        parent_message_id: row.get(7)?,
+        variant_index: row.get(8)?,
+        active: row.get(9)?,
+        // Unreadable JSON is dropped rather than failing the whole listing
+        generation_params: row
+            .get::<_, Option<String>>(10)?
+            .and_then(|raw| serde_json::from_str(&raw).ok()),
+        variant_count: row.get(11)?,
+    })
+}
+
+/// Messages of a conversation in order, only the active variant of each answer
+///
+/// Variants keep their original's timestamp and sort right after their parent.
+pub fn list_messages(conn: &Connection, conversation_id: i64) -> Result<Vec<Message>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE m.conversation_id = ?1 AND m.active = 1
+         ORDER BY m.created_at ASC, COALESCE(m.parent_message_id, m.id) ASC, m.id ASC",
+        MESSAGE_SELECT
+    ))?;
 
     let messages = stmt
-        .query_map([conversation_id], |row| {
-            Ok(Message {
-                id: row.get(0)?,
-                conversation_id: row.get(1)?,
-                role: row.get(2)?,
-                content: row.get(3)?,
-                created_at: row.get(4)?,
-            })
-        })?
+        .query_map([conversation_id], message_from_row)?
         .collect::<Result<Vec<_>>>()?;
     Ok(messages)
 }
 
+pub fn get_message(conn: &Connection, id: i64) -> Result<Message> {
+    let mut stmt = conn.prepare(&format!("{} WHERE m.id = ?1", MESSAGE_SELECT))?;
+    stmt.query_row([id], message_from_row)
+}
+
+/// Every variant in the group of `message_id`, by variant index
+pub fn list_variants(conn: &Connection, message_id: i64) -> Result<Vec<Message>> {
+    let message = get_message(conn, message_id)?;
+    let Some(parent_id) = message.parent_message_id else {
+        return Ok(vec![message]);
+    };
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE m.parent_message_id = ?1 ORDER BY m.variant_index ASC, m.id ASC",
+        MESSAGE_SELECT
+    ))?;
+    let variants = stmt
+        .query_map([parent_id], message_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(variants)
+}
+
+/// Store a new answer as the active variant next to `original_id`
+///
+/// `original_id` joins the group of `parent_id` first if it wasn't in one yet.
+pub fn add_variant(
+    conn: &mut Connection,
+    original_id: i64,
+    parent_id: i64,
+    content: &str,
+    finish_reason: Option<&str>,
+) -> Result<i64> {
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "UPDATE messages SET parent_message_id = ?1
+         WHERE id = ?2 AND parent_message_id IS NULL",
+        [parent_id, original_id],
+    )?;
+    tx.execute(
+        "UPDATE messages SET active = 0 WHERE parent_message_id = ?1",
+        [parent_id],
+    )?;
+    tx.execute(
+        "INSERT INTO messages (conversation_id, role, content, created_at, finish_reason,
+                               parent_message_id, variant_index, active)
+         SELECT o.conversation_id, 'assistant', ?1, o.created_at, ?2, ?3,
+                (SELECT COALESCE(MAX(variant_index), 0) + 1 FROM messages
+                 WHERE parent_message_id = ?3),
+                1
+         FROM messages o WHERE o.id = ?4",
+        rusqlite::params![content, finish_reason, parent_id, original_id],
+    )?;
+    if tx.changes() == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    let message_id = tx.last_insert_rowid();
+
+    tx.execute(
+        "UPDATE conversations SET updated_at = datetime('now')
+         WHERE id = (SELECT conversation_id FROM messages WHERE id = ?1)",
+        [message_id],
+    )?;
+
+    tx.commit()?;
+    Ok(message_id)
+}
+
+/// Make `message_id` the variant shown and sent as history
+pub fn set_active_variant(conn: &mut Connection, message_id: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+    let parent_id: Option<i64> = tx.query_row(
+        "SELECT parent_message_id FROM messages WHERE id = ?1",
+        [message_id],
+        |row| row.get(0),
+    )?;
+    // A message without alternatives is already the active one
+    if let Some(parent_id) = parent_id {
+        tx.execute(
+            "UPDATE messages SET active = (id = ?1) WHERE parent_message_id = ?2",
+            [message_id, parent_id],
+        )?;
+    }
+    tx.commit()
+}
+
 pub fn add_message(
     conn: &mut Connection,
     conversation_id: i64,
@@ -282,7 +591,349 @@ pub fn add_message(
     Ok(message_id)
 }
 
+pub fn set_message_finish_reason(
+    conn: &Connection,
+    message_id: i64,
+    finish_reason: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE messages SET finish_reason = ?1 WHERE id = ?2",
+        rusqlite::params![finish_reason, message_id],
+    )?;
+    Ok(())
+}
+
+pub fn set_message_generation_params(
+    conn: &Connection,
+    message_id: i64,
+    params: &GenerationParams,
+) -> Result<()> {
+    let json = serde_json::to_string(params)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "UPDATE messages SET generation_params = ?1 WHERE id = ?2",
+        rusqlite::params![json, message_id],
+    )?;
+    Ok(())
+}
+
+pub fn set_message_reconnected(conn: &Connection, message_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE messages SET reconnected = 1 WHERE id = ?1",
+        [message_id],
+    )?;
+    Ok(())
+}
+
+/// Append generated text to an existing message (used when continuing a cut-off answer)
+pub fn append_message_content(
+    conn: &mut Connection,
+    message_id: i64,
+    content: &str,
+    finish_reason: Option<&str>,
+) -> Result<()> {
+    let tx = conn.transaction()?;
+
+    let changed = tx.execute(
+        "UPDATE messages SET content = content || ?1, finish_reason = ?2 WHERE id = ?3",
+        rusqlite::params![content, finish_reason, message_id],
+    )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+
+    tx.execute(
+        "UPDATE conversations SET updated_at = datetime('now')
+         WHERE id = (SELECT conversation_id FROM messages WHERE id = ?1)",
+        [message_id],
+    )?;
+
+    tx.commit()
+}
+
+/// Message to insert as-is when importing a conversation
+#[derive(Debug)]
+pub struct ImportedMessage {
+    pub role: String,
+    pub content: String,
+    /// Original timestamp, `None` for now
+    pub created_at: Option<String>,
+    pub finish_reason: Option<String>,
+    pub generation_params: Option<GenerationParams>,
+}
+
+/// Create a conversation with its messages in one transaction
+pub fn import_conversation(
+    conn: &mut Connection,
+    params: ConversationParams,
+    messages: &[ImportedMessage],
+) -> Result<i64> {
+    let tx = conn.transaction()?;
+    let conversation_id = create_conversation(&tx, params)?;
+    for msg in messages {
+        let generation_params = msg
+            .generation_params
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        tx.execute(
+            "INSERT INTO messages (conversation_id, role, content, created_at, finish_reason,
+                                   generation_params)
+             VALUES (?1, ?2, ?3, COALESCE(?4, datetime('now')), ?5, ?6)",
+            rusqlite::params![
+                conversation_id,
+                msg.role,
+                msg.content,
+                msg.created_at,
+                msg.finish_reason,
+                generation_params
+            ],
+        )?;
+    }
+    tx.commit()?;
+    Ok(conversation_id)
+}
+
+/// Id of the group with this name, created if missing
+pub fn find_or_create_group(conn: &Connection, name: &str) -> Result<i64> {
+    let existing = conn
+        .query_row("SELECT id FROM groups WHERE name = ?1", [name], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    match existing {
+        Some(id) => Ok(id),
+        None => create_group(conn, name),
+    }
+}
+
 pub fn delete_conversation(conn: &Connection, id: i64) -> Result<()> {
     conn.execute("DELETE FROM conversations WHERE id = ?1", [id])?;
     Ok(())
 }
+
+/// Rebuild the database file, then fold the WAL back into it and truncate it
+///
+/// Needs exclusive use of the connection (no open transaction or statement).
+/// Returns false when a reader kept the checkpoint from completing.
+pub fn vacuum(conn: &Connection) -> Result<bool> {
+    conn.execute_batch("VACUUM")?;
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    Ok(busy == 0)
+}
+
+/// Number of (conversations, messages)
+pub fn count_rows(conn: &Connection) -> Result<(i64, i64)> {
+    let conversations =
+        conn.query_row("SELECT COUNT(*) FROM conversations", [], |row| row.get(0))?;
+    let messages = conn.query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))?;
+    Ok((conversations, messages))
+}
+
+/// Distinct presets used by conversations
+pub fn conversation_preset_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT DISTINCT preset_id FROM conversations ORDER BY preset_id")?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ids)
+}
+
+pub fn get_setting(conn: &Connection, key: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [key],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = datetime('now')",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+pub fn list_settings(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM app_settings ORDER BY key")?;
+    let settings = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(settings)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptTurn {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptSession {
+    pub id: i64,
+    pub preset_id: Option<String>,
+    /// What the user asked a system prompt for
+    pub intent: String,
+    pub history: Vec<PromptTurn>,
+    /// Prompt the dialogue ended with, `None` while still in progress
+    pub final_prompt: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn prompt_session_from_row(row: &rusqlite::Row) -> Result<PromptSession> {
+    let history: String = row.get(3)?;
+    let history = serde_json::from_str(&history).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(PromptSession {
+        id: row.get(0)?,
+        preset_id: row.get(1)?,
+        intent: row.get(2)?,
+        history,
+        final_prompt: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn history_json(history: &[PromptTurn]) -> Result<String> {
+    serde_json::to_string(history).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+const PROMPT_SESSION_SELECT: &str =
+    "SELECT id, preset_id, intent, history, final_prompt, created_at, updated_at
+     FROM prompt_sessions";
+
+/// Create a prompt session, or overwrite session `id` when given
+pub fn save_prompt_session(
+    conn: &Connection,
+    id: Option<i64>,
+    preset_id: Option<&str>,
+    intent: &str,
+    history: &[PromptTurn],
+    final_prompt: Option<&str>,
+) -> Result<i64> {
+    let history = history_json(history)?;
+    match id {
+        Some(id) => {
+            let changed = conn.execute(
+                "UPDATE prompt_sessions
+                 SET preset_id = ?1, intent = ?2, history = ?3, final_prompt = ?4,
+                     updated_at = datetime('now')
+                 WHERE id = ?5",
+                rusqlite::params![preset_id, intent, history, final_prompt, id],
+            )?;
+            if changed == 0 {
+                return Err(rusqlite::Error::QueryReturnedNoRows);
+            }
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO prompt_sessions (preset_id, intent, history, final_prompt)
+                 VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![preset_id, intent, history, final_prompt],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// Replace the history of a session after a dialogue round
+///
+/// `final_prompt` is only written when the round produced one.
+pub fn record_prompt_session_round(
+    conn: &Connection,
+    id: i64,
+    history: &[PromptTurn],
+    final_prompt: Option<&str>,
+) -> Result<()> {
+    let changed = conn.execute(
+        "UPDATE prompt_sessions
+         SET history = ?1, final_prompt = COALESCE(?2, final_prompt), updated_at = datetime('now')
+         WHERE id = ?3",
+        rusqlite::params![history_json(history)?, final_prompt, id],
+    )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
+    Ok(())
+}
+
+pub fn list_prompt_sessions(conn: &Connection) -> Result<Vec<PromptSession>> {
+    let mut stmt = conn.prepare(&format!(
+        "{} ORDER BY updated_at DESC, id DESC",
+        PROMPT_SESSION_SELECT
+    ))?;
+    let sessions = stmt
+        .query_map([], prompt_session_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(sessions)
+}
+
+pub fn get_prompt_session(conn: &Connection, id: i64) -> Result<PromptSession> {
+    let mut stmt = conn.prepare(&format!("{} WHERE id = ?1", PROMPT_SESSION_SELECT))?;
+    stmt.query_row([id], prompt_session_from_row)
+}
+
+/// Model registered from a file already in the models folder
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserPreset {
+    pub id: String,
+    /// File name without extension
+    pub label: String,
+    /// Relative to the models folder, `/`-separated
+    pub rel_path: String,
+    pub size_bytes: Option<i64>,
+    pub created_at: String,
+}
+
+const USER_PRESET_SELECT: &str =
+    "SELECT id, label, rel_path, size_bytes, created_at FROM user_presets";
+
+fn user_preset_from_row(row: &rusqlite::Row) -> Result<UserPreset> {
+    Ok(UserPreset {
+        id: row.get(0)?,
+        label: row.get(1)?,
+        rel_path: row.get(2)?,
+        size_bytes: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+pub fn list_user_presets(conn: &Connection) -> Result<Vec<UserPreset>> {
+    let mut stmt = conn.prepare(&format!("{} ORDER BY label, id", USER_PRESET_SELECT))?;
+    let presets = stmt
+        .query_map([], user_preset_from_row)?
+        .collect::<Result<Vec<_>>>()?;
+    Ok(presets)
+}
+
+pub fn get_user_preset(conn: &Connection, id: &str) -> Result<Option<UserPreset>> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", USER_PRESET_SELECT),
+        [id],
+        user_preset_from_row,
+    )
+    .optional()
+}
+
+pub fn add_user_preset(
+    conn: &Connection,
+    id: &str,
+    label: &str,
+    rel_path: &str,
+    size_bytes: Option<i64>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO user_presets (id, label, rel_path, size_bytes) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![id, label, rel_path, size_bytes],
+    )?;
+    Ok(())
+}
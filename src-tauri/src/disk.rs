@@ -0,0 +1,43 @@
+use crate::error::AppError;
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+
+/// Headroom kept free on top of the download size
+pub const DISK_MARGIN_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Free space on the volume holding `path`, `None` if the volume can't be identified
+pub fn available_space(path: &Path) -> Option<u64> {
+    let resolved = existing_ancestor(path)?.canonicalize().ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    // Most specific mount point wins (e.g. /home over /)
+    disks
+        .list()
+        .iter()
+        .filter(|d| resolved.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| d.available_space())
+}
+
+/// Fail with `InsufficientDiskSpace` when `required` bytes plus the margin don't fit in `dir`
+///
+/// If the volume can't be determined the check is skipped rather than blocking the download.
+pub fn ensure_free_space(dir: &Path, required: u64) -> Result<(), AppError> {
+    let Some(available) = available_space(dir) else {
+        return Ok(());
+    };
+    let needed = required.saturating_add(DISK_MARGIN_BYTES);
+    if available < needed {
+        return Err(AppError::InsufficientDiskSpace(format!(
+            "Insufficient disk space in {}: {} bytes required, {} bytes available",
+            dir.display(),
+            needed,
+            available
+        )));
+    }
+    Ok(())
+}
+
+/// Target directories may not exist yet, walk up to the first one that does
+fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors().find(|p| p.exists()).map(Path::to_path_buf)
+}
@@ -0,0 +1,143 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// Error returned by Tauri commands
+///
+/// Serialized as `{ "code": "...", "message": "..." }` so the frontend can
+/// branch on `code` instead of matching message substrings.
+#[derive(Debug)]
+pub enum AppError {
+    /// llama-server is not reachable (not started or crashed)
+    ServerNotRunning(String),
+    /// llama-server binary is missing
+    ServerNotInstalled(String),
+    /// llama-server answered with an error status or an unusable payload
+    ServerError(String),
+    /// Model output doesn't match the requested structured format
+    InvalidOutput(String),
+    /// A generation is already running for this conversation
+    AlreadyGenerating(String),
+    /// Model file for a preset is not on disk
+    ModelNotFound(String),
+    /// Model file is not a usable GGUF file
+    InvalidModel(String),
+    /// Preset id not present in pack-sources.json
+    UnknownPreset(String),
+    /// Model or binary download failed
+    DownloadFailed(String),
+    /// Not enough free space on the target volume
+    InsufficientDiskSpace(String),
+    /// Requested entity (download, conversation, ...) does not exist
+    NotFound(String),
+    /// Arguments rejected by validation
+    InvalidInput(String),
+    /// Path outside the allowed directories
+    AccessDenied(String),
+    /// Feature not available on this platform
+    Unsupported(String),
+    /// SQLite failure
+    Db(String),
+    /// Filesystem failure
+    Io(String),
+    /// Anything not covered above
+    Other(String),
+}
+
+impl AppError {
+    /// Stable machine-readable code
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ServerNotRunning(_) => "server_not_running",
+            AppError::ServerNotInstalled(_) => "server_not_installed",
+            AppError::ServerError(_) => "server_error",
+            AppError::InvalidOutput(_) => "invalid_output",
+            AppError::AlreadyGenerating(_) => "already_generating",
+            AppError::ModelNotFound(_) => "model_not_found",
+            AppError::InvalidModel(_) => "invalid_model",
+            AppError::UnknownPreset(_) => "unknown_preset",
+            AppError::DownloadFailed(_) => "download_failed",
+            AppError::InsufficientDiskSpace(_) => "insufficient_disk_space",
+            AppError::NotFound(_) => "not_found",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::AccessDenied(_) => "access_denied",
+            AppError::Unsupported(_) => "unsupported",
+            AppError::Db(_) => "db_error",
+            AppError::Io(_) => "io_error",
+            AppError::Other(_) => "other",
+        }
+    }
+
+    /// Human-readable message
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::ServerNotRunning(m)
+            | AppError::ServerNotInstalled(m)
+            | AppError::ServerError(m)
+            | AppError::InvalidOutput(m)
+            | AppError::AlreadyGenerating(m)
+            | AppError::ModelNotFound(m)
+            | AppError::InvalidModel(m)
+            | AppError::UnknownPreset(m)
+            | AppError::DownloadFailed(m)
+            | AppError::InsufficientDiskSpace(m)
+            | AppError::NotFound(m)
+            | AppError::InvalidInput(m)
+            | AppError::AccessDenied(m)
+            | AppError::Unsupported(m)
+            | AppError::Db(m)
+            | AppError::Io(m)
+            | AppError::Other(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", self.message())?;
+        s.end()
+    }
+}
+
+// Helpers still return `Result<_, String>`; those errors surface as `Other`
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(e.to_string()),
+            _ => AppError::Db(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Other(e.to_string())
+    }
+}
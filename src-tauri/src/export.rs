@@ -0,0 +1,267 @@
+use crate::db;
+use crate::error::AppError;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Version written to exported documents, bump on incompatible changes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Self-contained, round-trippable copy of one conversation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConversationExport {
+    pub version: u32,
+    pub exported_at: String,
+    pub conversation: ExportedConversation,
+    pub messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedConversation {
+    pub name: String,
+    #[serde(default)]
+    pub group_name: Option<String>,
+    pub preset_id: String,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    pub temperature: f32,
+    pub top_p: f32,
+    pub max_tokens: i32,
+    pub repeat_penalty: f32,
+    #[serde(default)]
+    pub dataset_ids: Option<String>,
+    #[serde(default)]
+    pub generation_timeout_secs: Option<i64>,
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<i32>,
+    #[serde(default)]
+    pub min_p: Option<f32>,
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub generation_params: Option<db::GenerationParams>,
+}
+
+pub fn export_conversation(conn: &Connection, id: i64) -> Result<ConversationExport, AppError> {
+    let conv = db::get_conversation(conn, id)?;
+    let messages = db::list_messages(conn, id)?
+        .into_iter()
+        .map(|m| ExportedMessage {
+            role: m.role,
+            content: m.content,
+            created_at: Some(m.created_at),
+            finish_reason: m.finish_reason,
+            generation_params: m.generation_params,
+        })
+        .collect();
+    Ok(ConversationExport {
+        version: FORMAT_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        conversation: ExportedConversation {
+            name: conv.name,
+            group_name: conv.group_name,
+            preset_id: conv.preset_id,
+            system_prompt: conv.system_prompt,
+            temperature: conv.temperature,
+            top_p: conv.top_p,
+            max_tokens: conv.max_tokens,
+            repeat_penalty: conv.repeat_penalty,
+            dataset_ids: conv.dataset_ids,
+            generation_timeout_secs: conv.generation_timeout_secs,
+            presence_penalty: conv.presence_penalty,
+            frequency_penalty: conv.frequency_penalty,
+            top_k: conv.top_k,
+            min_p: conv.min_p,
+            strict: conv.strict,
+            created_at: Some(conv.created_at),
+            updated_at: Some(conv.updated_at),
+        },
+        messages,
+    })
+}
+
+/// Human-readable rendering of an export, one section per message
+pub fn to_markdown(doc: &ConversationExport) -> String {
+    let conv = &doc.conversation;
+    let mut md = format!("# {}\n\n", conv.name);
+    md.push_str(&format!("*Model: {}", conv.preset_id));
+    if let Some(created_at) = &conv.created_at {
+        md.push_str(&format!(" · Created: {}", created_at));
+    }
+    md.push_str("*\n\n");
+    if let Some(system_prompt) = conv.system_prompt.as_deref().filter(|p| !p.is_empty()) {
+        md.push_str(&format!(
+            "## System prompt\n\n{}\n\n",
+            system_prompt.trim_end()
+        ));
+    }
+    for m in &doc.messages {
+        let role = if m.role == "user" {
+            "User"
+        } else {
+            "Assistant"
+        };
+        match &m.created_at {
+            Some(at) => md.push_str(&format!("## {} · {}\n\n", role, at)),
+            None => md.push_str(&format!("## {}\n\n", role)),
+        }
+        md.push_str(m.content.trim_end());
+        md.push_str("\n\n");
+    }
+    md
+}
+
+/// Create a file for conversation `id` in `dir` without replacing any existing one
+///
+/// Tries `<stem>.<ext>`, then `<stem> (<id>).<ext>`, then `<stem> (<id>-2).<ext>` and so on.
+pub fn create_export_file(
+    dir: &Path,
+    stem: &str,
+    id: i64,
+    extension: &str,
+) -> io::Result<(PathBuf, File)> {
+    let mut attempt = 0;
+    loop {
+        let name = match attempt {
+            0 => format!("{}.{}", stem, extension),
+            1 => format!("{} ({}).{}", stem, id, extension),
+            n => format!("{} ({}-{}).{}", stem, id, n, extension),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Conversation name made safe as a file name on every OS (without extension)
+pub fn file_stem(name: &str) -> String {
+    const MAX_CHARS: usize = 80;
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || "<>:\"/\\|?*".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_CHARS)
+        .collect();
+    // Windows drops trailing dots and spaces, which would merge distinct names
+    let stem = cleaned.trim().trim_end_matches('.').trim_end();
+    if stem.is_empty() {
+        "conversation".to_string()
+    } else if RESERVED.contains(&stem.to_ascii_uppercase().as_str()) {
+        format!("_{}", stem)
+    } else {
+        stem.to_string()
+    }
+}
+
+/// Recreate an exported conversation under a new id, returns that id
+pub fn import_conversation(
+    conn: &mut Connection,
+    doc: ConversationExport,
+) -> Result<i64, AppError> {
+    if doc.version == 0 || doc.version > FORMAT_VERSION {
+        return Err(AppError::Unsupported(format!(
+            "Unsupported export version {} (expected at most {})",
+            doc.version, FORMAT_VERSION
+        )));
+    }
+    if let Some(m) = doc
+        .messages
+        .iter()
+        .find(|m| m.role != "user" && m.role != "assistant")
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported message role: {}",
+            m.role
+        )));
+    }
+
+    let conv = doc.conversation;
+    let group_id = match conv.group_name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => Some(db::find_or_create_group(conn, name)?),
+        _ => None,
+    };
+    let params = db::ConversationParams {
+        name: conv.name,
+        group_id,
+        preset_id: conv.preset_id,
+        system_prompt: conv.system_prompt,
+        temperature: conv.temperature,
+        top_p: conv.top_p,
+        max_tokens: conv.max_tokens,
+        repeat_penalty: conv.repeat_penalty,
+        dataset_ids: conv.dataset_ids,
+        generation_timeout_secs: conv.generation_timeout_secs,
+        presence_penalty: conv.presence_penalty,
+        frequency_penalty: conv.frequency_penalty,
+        top_k: conv.top_k,
+        min_p: conv.min_p,
+        strict: conv.strict,
+    };
+    let messages: Vec<db::ImportedMessage> = doc
+        .messages
+        .into_iter()
+        .map(|m| db::ImportedMessage {
+            role: m.role,
+            content: m.content,
+            created_at: m.created_at,
+            finish_reason: m.finish_reason,
+            generation_params: m.generation_params,
+        })
+        .collect();
+    db::import_conversation(conn, params, &messages).map_err(AppError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_files_never_overwrite() {
+        let dir = std::env::temp_dir().join(format!("whytchat-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Taken by something else before the export
+        std::fs::write(dir.join("Notes (7).md"), b"keep").unwrap();
+
+        let names: Vec<String> = (0..3)
+            .map(|_| {
+                let (path, _) = create_export_file(&dir, "Notes", 7, "md").unwrap();
+                path.file_name().unwrap().to_string_lossy().to_string()
+            })
+            .collect();
+        assert_eq!(names, ["Notes.md", "Notes (7-2).md", "Notes (7-3).md"]);
+        assert_eq!(std::fs::read(dir.join("Notes (7).md")).unwrap(), b"keep");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
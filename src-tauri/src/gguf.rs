@@ -0,0 +1,39 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// Highest GGUF version llama.cpp currently writes
+const GGUF_MAX_VERSION: u32 = 3;
+
+/// Check the GGUF magic and version at the start of `path`
+///
+/// Catches truncated downloads, HTML error pages saved under a model name and
+/// other files llama-server would exit on immediately.
+pub fn validate_header(path: &Path) -> Result<(), String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header).map_err(|_| {
+        format!(
+            "Not a valid GGUF model (file too small): {}",
+            path.display()
+        )
+    })?;
+
+    if &header[..4] != GGUF_MAGIC {
+        return Err(format!(
+            "Not a valid GGUF model (bad magic): {}",
+            path.display()
+        ));
+    }
+    let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    if version == 0 || version > GGUF_MAX_VERSION {
+        return Err(format!(
+            "Not a valid GGUF model (unsupported version {}): {}",
+            version,
+            path.display()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use std::sync::RwLock;
+
+/// Proxy set by the user, takes precedence over HTTP_PROXY/HTTPS_PROXY
+static PROXY_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Hosts that never go through the configured proxy (local llama-server)
+const DEFAULT_NO_PROXY: &str = "localhost,127.0.0.1,::1";
+
+/// Currently configured proxy URL
+pub fn proxy_url() -> Option<String> {
+    PROXY_URL.read().ok().and_then(|p| p.clone())
+}
+
+pub fn set_proxy_url(url: Option<String>) {
+    if let Ok(mut p) = PROXY_URL.write() {
+        *p = url;
+    }
+}
+
+/// Proxy from the standard environment variables, for display only
+pub fn env_proxy_url() -> Option<String> {
+    [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+    ]
+    .iter()
+    .find_map(|k| std::env::var(k).ok().filter(|v| !v.is_empty()))
+}
+
+/// Build a `reqwest::Proxy` routing everything but local hosts through `url`
+pub fn build_proxy(url: &str) -> Result<reqwest::Proxy, String> {
+    let no_proxy =
+        reqwest::NoProxy::from_env().or_else(|| reqwest::NoProxy::from_string(DEFAULT_NO_PROXY));
+    Ok(reqwest::Proxy::all(url)
+        .map_err(|e| format!("Invalid proxy URL {}: {}", url, e))?
+        .no_proxy(no_proxy))
+}
+
+/// Client builder every outbound request must start from
+///
+/// Applies the configured proxy; without one reqwest falls back to the
+/// HTTP_PROXY/HTTPS_PROXY/NO_PROXY environment variables.
+pub fn client_builder() -> Result<reqwest::ClientBuilder, String> {
+    client_builder_with(proxy_url().as_deref())
+}
+
+/// Same as [`client_builder`] with an explicit proxy, used to test settings before saving
+pub fn client_builder_with(proxy: Option<&str>) -> Result<reqwest::ClientBuilder, String> {
+    let builder = reqwest::Client::builder();
+    match proxy {
+        Some(url) => Ok(builder.proxy(build_proxy(url)?)),
+        None => Ok(builder),
+    }
+}
@@ -0,0 +1,84 @@
+use whatlang::Lang;
+
+/// Language used when the text is too short or ambiguous to detect
+const FALLBACK: Lang = Lang::Eng;
+
+/// Below this whatlang is mostly guessing (e.g. on a couple of words)
+const MIN_CONFIDENCE: f64 = 0.12;
+
+/// Languages prompts are generated in, by ISO 639-1 code
+///
+/// Detection is restricted to them: models rarely write the others well and
+/// it avoids short inputs being taken for a close, rarer language.
+const LOCALE_CODES: [(&str, Lang); 24] = [
+    ("ar", Lang::Ara),
+    ("cs", Lang::Ces),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("es", Lang::Spa),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hu", Lang::Hun),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("ko", Lang::Kor),
+    ("nl", Lang::Nld),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("sv", Lang::Swe),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("zh", Lang::Cmn),
+];
+
+/// Language of a locale like `fr`, `pt-BR` or `deu`, `None` if unknown
+fn locale_lang(locale: &str) -> Option<Lang> {
+    let code = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    LOCALE_CODES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, lang)| *lang)
+        .or_else(|| Lang::from_code(code))
+}
+
+/// Language a generated prompt (or reply) should be written in
+///
+/// An explicit, recognized `locale` wins; otherwise the language of `text`
+/// is detected, falling back to English when it can't be told apart.
+pub fn resolve(locale: Option<&str>, text: &str) -> Lang {
+    locale
+        .and_then(locale_lang)
+        .or_else(|| {
+            let known = LOCALE_CODES.iter().map(|(_, lang)| *lang).collect();
+            whatlang::Detector::with_allowlist(known)
+                .detect(text)
+                .filter(|info| info.confidence() >= MIN_CONFIDENCE)
+                .map(|info| info.lang())
+        })
+        .unwrap_or(FALLBACK)
+}
+
+/// English name of `resolve`'s language, as told to the model
+pub fn prompt_language(locale: Option<&str>, text: &str) -> &'static str {
+    resolve(locale, text).eng_name()
+}
+
+/// ISO 639-1 code of a language, its 639-3 code when not in the table
+pub fn code(lang: Lang) -> &'static str {
+    LOCALE_CODES
+        .iter()
+        .find(|(_, l)| *l == lang)
+        .map(|(c, _)| *c)
+        .unwrap_or_else(|| lang.code())
+}
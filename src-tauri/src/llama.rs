@@ -6,7 +6,7 @@ pub struct ChatMessage {
     pub content: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
@@ -15,6 +15,33 @@ pub struct ChatCompletionRequest {
     pub top_p: f32,
     pub max_tokens: i32,
     pub repeat_penalty: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    /// OpenAI-style `{"type": "json_object"}` or `{"type": "json_schema", ...}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+    /// GBNF grammar constraining the output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<String>,
+    /// Sampling seed, llama-server picks a random one when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+}
+
+impl ChatCompletionRequest {
+    /// Whether `response_format` asks for JSON, so the result can be validated
+    pub fn expects_json(&self) -> bool {
+        self.response_format
+            .as_ref()
+            .and_then(|f| f["type"].as_str())
+            .is_some_and(|t| t == "json_object" || t == "json_schema")
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,15 +51,62 @@ pub struct SSEChunk {
 
 #[derive(Debug, Deserialize)]
 pub struct SSEChoice {
-    pub delta: SSEDelta,
+    /// Chat completions stream deltas
+    #[serde(default)]
+    pub delta: Option<SSEDelta>,
+    /// Raw completions stream text
+    #[serde(default)]
+    pub text: Option<String>,
     pub finish_reason: Option<String>,
 }
 
+impl SSEChoice {
+    /// Generated text of this chunk, for either endpoint
+    pub fn content(&self) -> Option<&str> {
+        self.delta
+            .as_ref()
+            .and_then(|d| d.content.as_deref())
+            .or(self.text.as_deref())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SSEDelta {
     pub content: Option<String>,
 }
 
+/// Request body of `/v1/completions` (raw prompt, no chat template)
+#[derive(Debug, Serialize, Clone)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub prompt: String,
+    pub stream: bool,
+    pub temperature: f32,
+    pub top_p: f32,
+    pub max_tokens: i32,
+    pub repeat_penalty: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+/// Message of an `{"error": ...}` frame sent inside an otherwise successful stream
+pub fn stream_error_message(data: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(data).ok()?;
+    let error = value.get("error")?;
+    Some(match error {
+        serde_json::Value::String(s) => s.clone(),
+        other => other
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| other.to_string()),
+    })
+}
+
 /// Get llama-server URL from environment or default
 pub fn get_server_url() -> String {
     if let Ok(url) = std::env::var("LLAMA_SERVER_URL") {
@@ -13,28 +13,23 @@ use tauri::{Emitter, Window};
 // Global process handle
 static LLAMA_PROCESS: Mutex<Option<Child>> = Mutex::new(None);
 static LOG_BUFFER: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+// Parameters the tracked process was launched with
+static SERVER_LAUNCH: Mutex<Option<ServerLaunch>> = Mutex::new(None);
 const LOG_CAPACITY: usize = 1000;
-
-/// Get the base directory for the application (workspace root in dev, exe dir in production)
-fn get_base_dir() -> Result<PathBuf, String> {
-    if cfg!(debug_assertions) {
-        // Use project root (parent of src-tauri) to ensure stable paths in dev
-        let src_tauri = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        Ok(src_tauri
-            .parent()
-            .ok_or("src-tauri has no parent")?
-            .to_path_buf())
-    } else {
-        Ok(std::env::current_exe()
-            .map_err(|e| format!("Failed to get exe path: {}", e))?
-            .parent()
-            .ok_or("No parent directory for exe")?
-            .to_path_buf())
-    }
+/// Log lines quoted in the error when llama-server dies right after starting
+const EXIT_LOG_LINES: usize = 12;
+/// Marks the beginning of a run in the log buffer
+const START_MARKER: &str = "[info] Starting llama-server";
+
+/// Get the base directory for the application (workspace root in dev, app data dir in production)
+pub fn get_base_dir() -> Result<PathBuf, String> {
+    crate::paths::base_dir()
 }
 
 // Download URLs for different platforms
 const LLAMA_VERSION: &str = "b6940";
+/// Upper bound for the release zip plus its extracted files, used for the disk-space preflight
+pub const SERVER_DOWNLOAD_ESTIMATE_BYTES: u64 = 512 * 1024 * 1024;
 const WIN_X64_URL: &str =
     "https://github.com/ggml-org/llama.cpp/releases/download/b6940/llama-b6940-bin-win-cpu-x64.zip";
 const LINUX_X64_URL: &str =
@@ -53,6 +48,27 @@ pub struct ServerStatus {
     pub pid: Option<u32>,
 }
 
+/// How the tracked llama-server process was started
+#[derive(Debug, Serialize, Clone)]
+pub struct ServerLaunch {
+    pub pid: u32,
+    pub model_path: String,
+    pub ctx_size: i32,
+    pub port: u16,
+    /// Full argument list passed to llama-server
+    pub args: Vec<String>,
+    pub started_at: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub asset_name: String,
+    pub url: String,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct DownloadProgress {
     pub downloaded: u64,
@@ -75,6 +91,18 @@ pub fn get_logs_snapshot() -> Vec<String> {
     guard.iter().cloned().collect()
 }
 
+/// Last `max` lines logged since the most recent start marker
+fn last_run_log_lines(max: usize) -> Vec<String> {
+    let guard = LOG_BUFFER.lock().unwrap();
+    let run_start = guard
+        .iter()
+        .rposition(|l| l.starts_with(START_MARKER))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let lines: Vec<String> = guard.iter().skip(run_start).cloned().collect();
+    lines[lines.len().saturating_sub(max)..].to_vec()
+}
+
 /// Clear in-memory logs
 pub fn clear_logs() {
     let mut guard = LOG_BUFFER.lock().unwrap();
@@ -83,9 +111,6 @@ pub fn clear_logs() {
 
 /// Get the path to the llama-server binary
 pub fn get_server_binary_path(_app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
-    // Keep binary within program folder
-    // In dev mode, current_dir() points to workspace root
-    // In production, use executable's parent directory
     let base = get_base_dir()?;
     let mut bin_path = base.join("llama-bin");
 
@@ -153,13 +178,64 @@ fn get_download_url() -> Result<&'static str, String> {
     }
 }
 
+/// Describe the release archive expected for this platform (for manual/offline installs)
+pub fn get_release_info() -> Result<ReleaseInfo, String> {
+    let url = get_download_url()?;
+    // URLs look like .../releases/download/<tag>/<asset>.zip
+    let mut segments = url.rsplit('/');
+    let asset_name = segments.next().unwrap_or_default().to_string();
+    let version = segments.next().unwrap_or(LLAMA_VERSION).to_string();
+
+    Ok(ReleaseInfo {
+        version,
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        asset_name,
+        url: url.to_string(),
+    })
+}
+
+/// Name of the server executable inside release archives
+fn server_binary_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "llama-server.exe"
+    } else {
+        "llama-server"
+    }
+}
+
+/// Ensure a ZIP archive contains the llama-server executable before extracting it
+pub fn validate_server_archive(zip_path: &Path) -> Result<(), String> {
+    let file = File::open(zip_path).map_err(|e| format!("Failed to open ZIP: {}", e))?;
+    let archive =
+        zip::ZipArchive::new(file).map_err(|e| format!("Failed to read ZIP archive: {}", e))?;
+
+    let target_name = server_binary_name();
+    let found = archive.file_names().any(|name| {
+        Path::new(name)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|s| s.eq_ignore_ascii_case(target_name))
+            .unwrap_or(false)
+    });
+
+    if !found {
+        return Err(format!(
+            "{} not found in archive {}",
+            target_name,
+            zip_path.display()
+        ));
+    }
+    Ok(())
+}
+
 /// Download llama-server binary with progress
 pub async fn download_server_binary(window: Window) -> Result<PathBuf, String> {
     let url = get_download_url()?;
 
     window.emit("llama-server-status", "downloading").ok();
 
-    // Create temp directory under program folder
+    // Create temp directory under the app base dir
     let base = get_base_dir()?;
     let temp_dir = base.join("downloads");
     fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create temp dir: {}", e))?;
@@ -167,7 +243,7 @@ pub async fn download_server_binary(window: Window) -> Result<PathBuf, String> {
     let zip_path = temp_dir.join(format!("llama-{}.zip", LLAMA_VERSION));
 
     // Download with progress
-    let client = reqwest::Client::builder()
+    let client = crate::http::client_builder()?
         .timeout(std::time::Duration::from_secs(300))
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
@@ -229,17 +305,13 @@ pub fn extract_server_binary(
     let mut archive =
         zip::ZipArchive::new(file).map_err(|e| format!("Failed to read ZIP archive: {}", e))?;
 
-    // Create bin directory within program folder
+    // Create bin directory within the app base dir
     let base = get_base_dir()?;
     let bin_dir = base.join("llama-bin");
     fs::create_dir_all(&bin_dir).map_err(|e| format!("Failed to create bin dir: {}", e))?;
 
     // Find and extract llama-server executable and all required DLLs
-    let target_name = if cfg!(target_os = "windows") {
-        "llama-server.exe"
-    } else {
-        "llama-server"
-    };
+    let target_name = server_binary_name();
 
     let mut found = false;
 
@@ -288,12 +360,9 @@ pub fn extract_server_binary(
     }
 
     if !found {
-        return Err(format!("{} not found in downloaded archive", target_name));
+        return Err(format!("{} not found in archive", target_name));
     }
 
-    // Cleanup temp file
-    fs::remove_file(zip_path).ok();
-
     get_server_binary_path(app_handle)
 }
 
@@ -339,7 +408,7 @@ pub fn start_server_process(
         return Err("llama-server binary not found. Please install it first.".to_string());
     }
 
-    // Check if model exists within program folder
+    // Check if model exists within the app base dir
     let base = get_base_dir()?;
     let model_full_path = base.join(&model_path);
 
@@ -405,15 +474,18 @@ pub fn start_server_process(
             .env("WINDIR", &system_root);
     }
 
-    command
-        .arg("-m")
-        .arg(model_full_path.to_string_lossy().as_ref())
-        .arg("--port")
-        .arg(port.to_string())
-        .arg("--ctx-size")
-        .arg(ctx_size.to_string())
+    let args = vec![
+        "-m".to_string(),
+        model_full_path.to_string_lossy().to_string(),
+        "--port".to_string(),
+        port.to_string(),
+        "--ctx-size".to_string(),
+        ctx_size.to_string(),
         // Enable embeddings endpoint for RAG features
-        .arg("--embeddings")
+        "--embeddings".to_string(),
+    ];
+    command
+        .args(&args)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
@@ -431,6 +503,10 @@ pub fn start_server_process(
 
     let pid = child.id();
     eprintln!("[llama_install] Process spawned with PID: {}", pid);
+    {
+        let guard = LOG_BUFFER.lock().unwrap();
+        push_log_line(guard, &window, format!("{} (PID {})", START_MARKER, pid));
+    }
 
     // Spawn reader threads to capture logs
     if let Some(stdout) = child.stdout.take() {
@@ -473,7 +549,18 @@ pub fn start_server_process(
                         status
                     );
                     *guard = None;
-                    return Err("llama-server process exited immediately. Please verify dependencies and DLLs.".to_string());
+                    drop(guard);
+                    // Let the reader threads drain what the process printed before dying
+                    std::thread::sleep(std::time::Duration::from_millis(300));
+                    let lines = last_run_log_lines(EXIT_LOG_LINES);
+                    if lines.is_empty() {
+                        return Err("llama-server process exited immediately. Please verify dependencies and DLLs.".to_string());
+                    }
+                    return Err(format!(
+                        "llama-server process exited immediately ({}). Last output:\n{}",
+                        status,
+                        lines.join("\n")
+                    ));
                 }
                 Ok(None) => {
                     eprintln!("[llama_install] Process is still running - OK!");
@@ -485,11 +572,34 @@ pub fn start_server_process(
         }
     }
 
+    *SERVER_LAUNCH.lock().unwrap() = Some(ServerLaunch {
+        pid,
+        model_path: model_full_path.to_string_lossy().to_string(),
+        ctx_size,
+        port,
+        args,
+        started_at: chrono::Utc::now().to_rfc3339(),
+    });
     window.emit("llama-server-status", "running").ok();
 
     Ok(pid)
 }
 
+/// Launch parameters of the tracked process, `None` when it isn't running
+pub fn running_server_launch() -> Option<ServerLaunch> {
+    let mut guard = LLAMA_PROCESS.lock().ok()?;
+    let alive = match guard.as_mut() {
+        Some(child) => matches!(child.try_wait(), Ok(None)),
+        None => false,
+    };
+    if !alive {
+        *guard = None;
+        *SERVER_LAUNCH.lock().ok()? = None;
+        return None;
+    }
+    SERVER_LAUNCH.lock().ok()?.clone()
+}
+
 /// Stop llama-server process
 pub fn stop_server_process(window: Window) -> Result<(), String> {
     eprintln!("[llama_install] ====== STOP SERVER REQUESTED ======");
@@ -499,6 +609,7 @@ pub fn stop_server_process(window: Window) -> Result<(), String> {
         .map_err(|e| format!("Lock error: {}", e))?;
 
     if let Some(mut child) = guard.take() {
+        *SERVER_LAUNCH.lock().unwrap() = None;
         let pid = child.id();
         eprintln!("[llama_install] Killing server process PID: {}", pid);
         window.emit("llama-server-status", "stopping").ok();
@@ -4,38 +4,178 @@
     windows_subsystem = "windows"
 )]
 
+mod backup;
 mod db;
+mod disk;
+mod error;
+mod export;
+mod gguf;
+mod http;
+mod lang;
 mod llama;
 mod llama_install;
-
-use futures_util::StreamExt;
+mod opacity;
+mod paths;
+mod prompt_templates;
+mod reveal;
+mod settings;
+mod sse;
+mod tray;
+
+use error::AppError;
+use futures_util::{
+    future::{AbortHandle, AbortRegistration, Abortable},
+    StreamExt,
+};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
-    path::PathBuf,
+    io::{Read, Write},
+    path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, Mutex,
     },
 };
 use sysinfo::System;
 use tauri::{
-    AppHandle, Emitter, LogicalPosition, LogicalSize, Manager, Position, Size, State, Window,
-    WindowEvent,
+    AppHandle, Emitter, LogicalPosition, LogicalSize, Manager, Monitor, Position, Size, State,
+    Window, WindowEvent,
 };
 use tauri_plugin_updater::UpdaterExt;
 use tokio::{fs as afs, io::AsyncWriteExt};
 
 struct OverlayState(Mutex<bool>);
 
+/// Abort handles of the prompt-dialogue rounds in flight, keyed by round number
+#[derive(Default)]
+struct PromptDialogueState {
+    next_round: AtomicU64,
+    rounds: Mutex<HashMap<u64, AbortHandle>>,
+}
+
+impl PromptDialogueState {
+    /// Track a round until the returned guard is dropped
+    fn register(&self) -> Result<(DialogueRound<'_>, AbortRegistration), AppError> {
+        let (handle, registration) = AbortHandle::new_pair();
+        let round = self.next_round.fetch_add(1, Ordering::SeqCst);
+        self.rounds
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(round, handle);
+        Ok((DialogueRound { state: self, round }, registration))
+    }
+
+    /// Abort every round in flight, returns how many there were
+    fn cancel_all(&self) -> Result<usize, AppError> {
+        let mut rounds = self.rounds.lock().map_err(|e| e.to_string())?;
+        let count = rounds.len();
+        for (_, handle) in rounds.drain() {
+            handle.abort();
+        }
+        Ok(count)
+    }
+}
+
+struct DialogueRound<'a> {
+    state: &'a PromptDialogueState,
+    round: u64,
+}
+
+impl Drop for DialogueRound<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut rounds) = self.state.rounds.lock() {
+            rounds.remove(&self.round);
+        }
+    }
+}
+
 struct DbState(Mutex<Connection>);
 
 struct DownloadManager {
     inner: Mutex<HashMap<String, DownloadEntry>>,
 }
 
+/// In-flight generations, keyed by request id
+#[derive(Default)]
+struct GenerationManager {
+    active: Mutex<HashMap<String, GenerationSlot>>,
+}
+
+struct GenerationSlot {
+    /// Conversation written to, `None` for generations outside a chat
+    conversation_id: Option<i64>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl GenerationManager {
+    /// Track a generation until the returned guard is dropped
+    ///
+    /// Fails with `AlreadyGenerating` if the conversation already has one running,
+    /// two streams into the same transcript would interleave their messages.
+    fn register(
+        &self,
+        request_id: &str,
+        conversation_id: Option<i64>,
+    ) -> Result<ActiveGeneration<'_>, AppError> {
+        let mut active = self.active.lock().map_err(|e| e.to_string())?;
+        if let Some(id) = conversation_id {
+            if active.values().any(|s| s.conversation_id == Some(id)) {
+                return Err(AppError::AlreadyGenerating(format!(
+                    "Conversation {} is already generating",
+                    id
+                )));
+            }
+        }
+        if active.contains_key(request_id) {
+            return Err(AppError::AlreadyGenerating(format!(
+                "Request {} is already running",
+                request_id
+            )));
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        active.insert(
+            request_id.to_string(),
+            GenerationSlot {
+                conversation_id,
+                cancel: cancel.clone(),
+            },
+        );
+        Ok(ActiveGeneration {
+            manager: self,
+            request_id: request_id.to_string(),
+            cancel,
+        })
+    }
+
+    /// Signal every in-flight generation, returns how many there were
+    fn cancel_all(&self) -> usize {
+        let Ok(active) = self.active.lock() else {
+            return 0;
+        };
+        for slot in active.values() {
+            slot.cancel.store(true, Ordering::SeqCst);
+        }
+        active.len()
+    }
+}
+
+struct ActiveGeneration<'a> {
+    manager: &'a GenerationManager,
+    request_id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for ActiveGeneration<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.manager.active.lock() {
+            active.remove(&self.request_id);
+        }
+    }
+}
+
 /// System information response structure for onboarding wizard
 #[derive(Serialize)]
 struct SystemInfo {
@@ -61,18 +201,20 @@ struct SystemInfo {
 /// This command only reads local system specs. No data is transmitted
 /// over the network. Execution requires explicit user consent via UI.
 #[tauri::command]
-fn system_info() -> Result<SystemInfo, String> {
+fn system_info() -> Result<SystemInfo, AppError> {
     let mut sys = System::new_all();
     sys.refresh_all();
 
     let cores = sys.cpus().len();
     if cores == 0 {
-        return Err("Unable to detect CPU cores".to_string());
+        return Err(AppError::Other("Unable to detect CPU cores".to_string()));
     }
 
     let ram_bytes = sys.total_memory();
     if ram_bytes == 0 {
-        return Err("Unable to detect system memory".to_string());
+        return Err(AppError::Other(
+            "Unable to detect system memory".to_string(),
+        ));
     }
 
     const GB: u64 = 1024 * 1024 * 1024;
@@ -91,12 +233,203 @@ fn system_info() -> Result<SystemInfo, String> {
     })
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Feasibility {
+    Ok,
+    Tight,
+    WontFit,
+    /// The pack doesn't declare its size, nothing to compare
+    Unknown,
+}
+
+#[derive(Serialize)]
+struct FeasibilityReport {
+    verdict: Feasibility,
+    explanation: String,
+    /// Estimated memory needed to run the preset (weights + KV cache + overhead)
+    required_bytes: Option<u64>,
+    total_ram_bytes: u64,
+    available_ram_bytes: u64,
+}
+
+/// Estimate whether this machine can run a preset, before downloading it
+///
+/// Advisory only: compares the pack size plus a KV-cache estimate for the
+/// preset's declared context against system RAM. GPU memory isn't detected,
+/// so a machine with a large GPU may do better than reported.
+#[tauri::command]
+fn check_preset_feasibility(preset_id: String) -> Result<FeasibilityReport, AppError> {
+    // Rough f16 KV cache cost per context token for a 7-8B model
+    const KV_BYTES_PER_TOKEN: u64 = 128 * 1024;
+    // llama-server runtime, compute buffers, OS file cache slack
+    const OVERHEAD_BYTES: u64 = 512 * 1024 * 1024;
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+    const PRESETS_JSON: &str = include_str!("../presets.json");
+    let presets: Vec<PresetInternal> =
+        serde_json::from_str(PRESETS_JSON).map_err(|e| e.to_string())?;
+    let preset = presets
+        .into_iter()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| AppError::UnknownPreset(format!("Unknown preset: {}", preset_id)))?;
+    const PACKS_JSON: &str = include_str!("../pack-sources.json");
+    let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
+    let size_bytes = packs
+        .into_iter()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| AppError::UnknownPreset(format!("Unknown preset: {}", preset_id)))?
+        .size_bytes;
+
+    let mut sys = System::new();
+    sys.refresh_memory();
+    let total_ram_bytes = sys.total_memory();
+    let available_ram_bytes = sys.available_memory();
+
+    let Some(size_bytes) = size_bytes else {
+        return Ok(FeasibilityReport {
+            verdict: Feasibility::Unknown,
+            explanation: "The model size isn't known for this preset".to_string(),
+            required_bytes: None,
+            total_ram_bytes,
+            available_ram_bytes,
+        });
+    };
+    let required = size_bytes
+        .saturating_add(preset.context as u64 * KV_BYTES_PER_TOKEN)
+        .saturating_add(OVERHEAD_BYTES);
+    let needed_gb = required as f64 / GB;
+    let (verdict, explanation) = if required > total_ram_bytes {
+        (
+            Feasibility::WontFit,
+            format!(
+                "Needs about {:.1} GB of memory but this machine has {:.1} GB in total",
+                needed_gb,
+                total_ram_bytes as f64 / GB
+            ),
+        )
+    } else if required > available_ram_bytes || required > total_ram_bytes / 4 * 3 {
+        (
+            Feasibility::Tight,
+            format!(
+                "Needs about {:.1} GB of memory, {:.1} GB is free right now: expect slowdowns or close other apps",
+                needed_gb,
+                available_ram_bytes as f64 / GB
+            ),
+        )
+    } else {
+        (
+            Feasibility::Ok,
+            format!(
+                "Needs about {:.1} GB of memory, {:.1} GB is free",
+                needed_gb,
+                available_ram_bytes as f64 / GB
+            ),
+        )
+    };
+    Ok(FeasibilityReport {
+        verdict,
+        explanation,
+        required_bytes: Some(required),
+        total_ram_bytes,
+        available_ram_bytes,
+    })
+}
+
 /// Enable/disable OS-level click-through on the window (ignore cursor events)
 #[tauri::command]
-async fn set_click_through(window: Window, enabled: bool) -> Result<(), String> {
+async fn set_click_through(window: Window, enabled: bool) -> Result<(), AppError> {
     window
         .set_ignore_cursor_events(enabled)
-        .map_err(|e| e.to_string())
+        .map_err(|e| AppError::Other(e.to_string()))
+}
+
+/// Monitor under the cursor, falling back to the primary (or any) monitor
+fn active_monitor(window: &Window) -> Result<Monitor, AppError> {
+    let under_cursor = window
+        .cursor_position()
+        .ok()
+        .and_then(|p| window.monitor_from_point(p.x, p.y).ok().flatten());
+    under_cursor
+        .or_else(|| window.primary_monitor().ok().flatten())
+        .or_else(|| {
+            window
+                .available_monitors()
+                .ok()
+                .and_then(|m| m.into_iter().next())
+        })
+        .ok_or_else(|| AppError::Other("No monitor detected".to_string()))
+}
+
+/// Logical (x, y, width, height) of a monitor
+fn monitor_logical_rect(monitor: &Monitor) -> (f64, f64, f64, f64) {
+    let scale = monitor.scale_factor();
+    let pos = monitor.position();
+    let size = monitor.size();
+    (
+        pos.x as f64 / scale,
+        pos.y as f64 / scale,
+        size.width as f64 / scale,
+        size.height as f64 / scale,
+    )
+}
+
+/// Keep a window of logical size `w`x`h` at (x, y) fully on a connected monitor
+///
+/// A position on any connected monitor stays on that monitor; anything else
+/// (e.g. saved on a display that's gone) is pulled onto the active monitor.
+fn clamp_to_monitors(
+    window: &Window,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+) -> Result<(f64, f64), AppError> {
+    let monitors = window.available_monitors().unwrap_or_default();
+    let target = match monitors.into_iter().find(|m| {
+        let (mx, my, mw, mh) = monitor_logical_rect(m);
+        x >= mx && x < mx + mw && y >= my && y < my + mh
+    }) {
+        Some(m) => m,
+        None => active_monitor(window)?,
+    };
+    let (mx, my, mw, mh) = monitor_logical_rect(&target);
+    let cx = x.min(mx + mw - w).max(mx);
+    let cy = y.min(my + mh - h).max(my);
+    Ok((cx, cy))
+}
+
+/// Current logical size of the window
+fn window_logical_size(window: &Window) -> Result<(f64, f64), AppError> {
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    Ok((size.width as f64 / scale, size.height as f64 / scale))
+}
+
+/// Set the window opacity (0.2 to 1.0) and remember it for the next launch
+#[tauri::command]
+async fn set_window_opacity(
+    level: f64,
+    window: Window,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    if !(opacity::MIN_OPACITY..=opacity::MAX_OPACITY).contains(&level) {
+        return Err(AppError::InvalidInput(format!(
+            "Opacity must be between {} and {}",
+            opacity::MIN_OPACITY,
+            opacity::MAX_OPACITY
+        )));
+    }
+    opacity::apply(&window, level)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::set(&conn, settings::WINDOW_OPACITY, &serde_json::json!(level))?;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_window_opacity(db: State<'_, DbState>) -> Result<f64, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(settings::get_f64(&conn, settings::WINDOW_OPACITY).unwrap_or(opacity::MAX_OPACITY))
 }
 
 #[tauri::command]
@@ -106,22 +439,38 @@ async fn apply_overlay_bounds(
     height: Option<f64>,
     x: Option<i32>,
     y: Option<i32>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     if let (Some(w), Some(h)) = (width, height) {
         window
             .set_size(Size::Logical(LogicalSize::new(w, h)))
             .map_err(|e| e.to_string())?;
     }
     if let (Some(px), Some(py)) = (x, y) {
+        let (w, h) = match (width, height) {
+            (Some(w), Some(h)) => (w, h),
+            _ => window_logical_size(&window)?,
+        };
+        let (cx, cy) = clamp_to_monitors(&window, px as f64, py as f64, w, h)?;
         window
-            .set_position(Position::Logical(LogicalPosition::new(
-                px as f64, py as f64,
-            )))
+            .set_position(Position::Logical(LogicalPosition::new(cx, cy)))
             .map_err(|e| e.to_string())?;
     }
     Ok(())
 }
 
+/// Center the window on the monitor under the cursor (primary as fallback)
+#[tauri::command]
+async fn center_overlay_on_active_monitor(window: Window) -> Result<(), AppError> {
+    let monitor = active_monitor(&window)?;
+    let (mx, my, mw, mh) = monitor_logical_rect(&monitor);
+    let (w, h) = window_logical_size(&window)?;
+    let x = mx + ((mw - w) / 2.0).max(0.0);
+    let y = my + ((mh - h) / 2.0).max(0.0);
+    window
+        .set_position(Position::Logical(LogicalPosition::new(x, y)))
+        .map_err(|e| AppError::Other(e.to_string()))
+}
+
 #[derive(Serialize, Clone)]
 struct DownloadState {
     filename: String,
@@ -134,10 +483,25 @@ struct DownloadState {
 struct DownloadEntry {
     state: DownloadState,
     cancel: Arc<AtomicBool>,
+    /// Stops the task but keeps the `.part` to resume from
+    pause: Arc<AtomicBool>,
+    /// Partial file of a transfer, `None` when nothing was downloaded
+    part_path: Option<PathBuf>,
+}
+
+impl DownloadManager {
+    /// State of every tracked download, copied under the lock
+    fn snapshot(&self) -> Result<HashMap<String, DownloadState>, AppError> {
+        let map = self.inner.lock().map_err(|e| e.to_string())?;
+        Ok(map
+            .iter()
+            .map(|(preset_id, entry)| (preset_id.clone(), entry.state.clone()))
+            .collect())
+    }
 }
 
 #[tauri::command]
-async fn toggle_overlay(window: Window, state: State<'_, OverlayState>) -> Result<(), String> {
+async fn toggle_overlay(window: Window, state: State<'_, OverlayState>) -> Result<(), AppError> {
     let mut flag = state.0.lock().map_err(|_| "lock".to_string())?;
     *flag = !*flag;
     window.set_always_on_top(*flag).map_err(|e| e.to_string())?;
@@ -149,7 +513,7 @@ async fn set_overlay_mode(
     window: Window,
     state: State<'_, OverlayState>,
     enabled: bool,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     {
         let mut flag = state.0.lock().map_err(|_| "lock".to_string())?;
         *flag = enabled;
@@ -174,22 +538,63 @@ struct ImportArgs {
     preset_id: String,
     #[serde(rename = "sourcePath")]
     source_path: String,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// A preset id is safe to use as a directory name if it is a known pack
+/// or made only of `[a-zA-Z0-9_-]`
+fn is_valid_preset_id(preset_id: &str) -> bool {
+    const PACKS_JSON: &str = include_str!("../pack-sources.json");
+    let known = serde_json::from_str::<Vec<PackSource>>(PACKS_JSON)
+        .map(|packs| packs.iter().any(|p| p.id == preset_id))
+        .unwrap_or(false);
+    known
+        || (!preset_id.is_empty()
+            && preset_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
 }
 
 #[tauri::command]
-async fn import_pack(args: ImportArgs, app: AppHandle) -> Result<String, String> {
-    let target_dir: PathBuf = models_root_dir(&app)?.join(&args.preset_id);
-    fs::create_dir_all(&target_dir).map_err(|e| e.to_string())?;
+async fn import_pack(args: ImportArgs, app: AppHandle) -> Result<String, AppError> {
+    if !is_valid_preset_id(&args.preset_id) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid preset id: {}",
+            args.preset_id
+        )));
+    }
 
     let src = PathBuf::from(&args.source_path);
-    if !src.exists() {
-        return Err("Source file not found".to_string());
+    if !src.is_file() {
+        return Err(AppError::NotFound("Source file not found".to_string()));
     }
     let file_name = src
         .file_name()
-        .ok_or_else(|| "Invalid file name".to_string())?;
-    let dest = target_dir.join(file_name);
-    fs::copy(&src, &dest).map_err(|e| e.to_string())?;
+        .ok_or_else(|| AppError::InvalidInput("Invalid file name".to_string()))?;
+
+    let models_root = models_root_dir(&app)?;
+    let target_dir: PathBuf = models_root.join(&args.preset_id);
+    fs::create_dir_all(&target_dir)?;
+
+    // Resolve symlinks/`..` and make sure we are still inside the models root
+    let canonical_root = models_root.canonicalize()?;
+    let canonical_dir = target_dir.canonicalize()?;
+    if !canonical_dir.starts_with(&canonical_root) {
+        return Err(AppError::AccessDenied(format!(
+            "Refusing to import outside the models directory: {}",
+            canonical_dir.display()
+        )));
+    }
+
+    let dest = canonical_dir.join(file_name);
+    if dest.exists() && !args.overwrite {
+        return Err(AppError::InvalidInput(format!(
+            "Model file already exists: {}. Pass overwrite to replace it.",
+            dest.display()
+        )));
+    }
+    fs::copy(&src, &dest)?;
     Ok(dest.to_string_lossy().to_string())
 }
 
@@ -205,13 +610,13 @@ struct StartResult {
 }
 
 #[tauri::command]
-async fn start_llama(args: StartArgs, _app: AppHandle) -> Result<StartResult, String> {
+async fn start_llama(args: StartArgs, _app: AppHandle) -> Result<StartResult, AppError> {
     const PACKS_JSON: &str = include_str!("../pack-sources.json");
     let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
     let pack = packs
         .into_iter()
         .find(|p| p.id == args.preset_id)
-        .ok_or_else(|| "Unknown preset".to_string())?;
+        .ok_or_else(|| AppError::UnknownPreset(format!("Unknown preset: {}", args.preset_id)))?;
     let final_path = models_root_dir(&_app)?.join(&pack.id).join(&pack.filename);
     let need = !final_path.exists();
 
@@ -249,15 +654,17 @@ struct PresetPublic {
     desc_key: String,
     #[serde(rename = "useCases")]
     use_cases: Vec<String>,
+    /// Registered by `scan_models`: `labelKey` is the file name, `descKey` its path
+    custom: bool,
 }
 
 #[tauri::command]
-async fn get_presets() -> Result<Vec<PresetPublic>, String> {
+async fn get_presets(app: AppHandle) -> Result<Vec<PresetPublic>, AppError> {
     const PRESETS_JSON: &str = include_str!("../presets.json");
     let data: Vec<PresetInternal> =
         serde_json::from_str(PRESETS_JSON).map_err(|e| e.to_string())?;
 
-    let list: Vec<PresetPublic> = data
+    let mut list: Vec<PresetPublic> = data
         .into_iter()
         .filter(|p| {
             // Hide phi3_local in production builds
@@ -272,70 +679,325 @@ async fn get_presets() -> Result<Vec<PresetPublic>, String> {
             label_key: p.label_key,
             desc_key: p.desc_key,
             use_cases: p.use_cases,
+            custom: false,
         })
         .collect();
+    list.extend(
+        installed_user_presets(&app)
+            .into_iter()
+            .map(|p| PresetPublic {
+                id: p.id,
+                label_key: p.label,
+                desc_key: p.rel_path,
+                use_cases: Vec::new(),
+                custom: true,
+            }),
+    );
     Ok(list)
 }
 
+/// Registered user presets whose file is still there
+fn installed_user_presets(app: &AppHandle) -> Vec<db::UserPreset> {
+    let presets = app
+        .try_state::<DbState>()
+        .and_then(|db| {
+            let conn = db.0.lock().ok()?;
+            db::list_user_presets(&conn).ok()
+        })
+        .unwrap_or_default();
+    // Resolved after the lock is released, it reads settings
+    let Ok(root) = models_root_dir(app) else {
+        return Vec::new();
+    };
+    presets
+        .into_iter()
+        .filter(|p| root.join(&p.rel_path).is_file())
+        .collect()
+}
+
 /// Helper function to get the root directory for models
-/// Keep models within program folder for portability
-fn models_root_dir(_app: &AppHandle) -> Result<PathBuf, String> {
-    // In dev: use project root (parent of src-tauri) via compile-time CARGO_MANIFEST_DIR
-    // In prod: use executable directory
-    let base = if cfg!(debug_assertions) {
-        let src_tauri = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        src_tauri
-            .parent()
-            .ok_or("src-tauri has no parent")?
-            .to_path_buf()
-    } else {
-        std::env::current_exe()
-            .map_err(|e| format!("Failed to get exe path: {}", e))?
-            .parent()
-            .ok_or("No parent directory for exe")?
-            .to_path_buf()
+/// Models directory from settings, or `<base dir>/models` when unset
+fn models_root_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let configured = app.try_state::<DbState>().and_then(|db| {
+        let conn = db.0.lock().ok()?;
+        settings::get_string(&conn, settings::MODELS_DIR)
+    });
+    match configured {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(paths::base_dir()?.join("models")),
+    }
+}
+
+/// Make sure `dir` exists and we can create files in it
+fn ensure_writable_dir(dir: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(dir)
+        .map_err(|e| AppError::AccessDenied(format!("Cannot create {}: {}", dir.display(), e)))?;
+    let probe = dir.join(".whytchat-write-test");
+    fs::write(&probe, b"ok")
+        .map_err(|e| AppError::AccessDenied(format!("{} is not writable: {}", dir.display(), e)))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_models_dir(app: AppHandle) -> Result<String, AppError> {
+    Ok(models_root_dir(&app)?.to_string_lossy().to_string())
+}
+
+/// Store models in `path` from now on (`None`/empty restores the default)
+///
+/// Existing models are not moved. Returns the directory now in use.
+#[tauri::command]
+fn set_models_dir(
+    path: Option<String>,
+    app: AppHandle,
+    db: State<'_, DbState>,
+) -> Result<String, AppError> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(p) = &path {
+        let dir = Path::new(p);
+        if !dir.is_absolute() {
+            return Err(AppError::InvalidInput(
+                "Models directory must be an absolute path".to_string(),
+            ));
+        }
+        ensure_writable_dir(dir)?;
+    }
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        settings::set(&conn, settings::MODELS_DIR, &serde_json::json!(path))?;
+    }
+    get_models_dir(app)
+}
+
+/// Upper bound on what read_file_content returns, larger files are truncated
+const MAX_READ_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Files the user explicitly picked through `pick_file_to_read`
+struct FileAccessScope(Mutex<HashSet<PathBuf>>);
+
+/// Directories read_file_content may always read from (app data and models)
+fn allowed_read_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(db_path) = db::get_db_path(app) {
+        if let Some(data_dir) = db_path.parent() {
+            roots.push(data_dir.to_path_buf());
+        }
+    }
+    if let Ok(models) = models_root_dir(app) {
+        roots.push(models);
+    }
+    // Canonicalize so comparisons match canonicalized inputs; skip roots that don't exist yet
+    roots
+        .into_iter()
+        .filter_map(|r| r.canonicalize().ok())
+        .collect()
+}
+
+/// Open a native file picker and grant read access to the chosen file
+#[tauri::command]
+async fn pick_file_to_read(
+    app: AppHandle,
+    scope: State<'_, FileAccessScope>,
+) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let picked = app
+        .dialog()
+        .file()
+        .add_filter(
+            "Documents",
+            &[
+                "txt", "md", "json", "csv", "log", "js", "ts", "tsx", "jsx", "py", "html", "css",
+                "xml",
+            ],
+        )
+        .add_filter("All Files", &["*"])
+        .blocking_pick_file();
+
+    let Some(file) = picked else {
+        return Ok(None);
     };
-    eprintln!("[models_root_dir] Base path: {:?}", base);
-    Ok(base.join("models"))
+    let path = file.into_path().map_err(|e| e.to_string())?;
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| AppError::Io(format!("Failed to resolve {}: {}", path.display(), e)))?;
+
+    scope
+        .0
+        .lock()
+        .map_err(|_| "lock".to_string())?
+        .insert(canonical.clone());
+    Ok(Some(canonical.to_string_lossy().to_string()))
+}
+
+/// Allow a canonical path picked by the user or under one of `allowed_read_roots`
+fn ensure_file_access(
+    canonical: &Path,
+    path: &str,
+    app: &AppHandle,
+    scope: &FileAccessScope,
+) -> Result<(), AppError> {
+    let granted = scope
+        .0
+        .lock()
+        .map_err(|_| "lock".to_string())?
+        .contains(canonical);
+    if !granted
+        && !allowed_read_roots(app)
+            .iter()
+            .any(|root| canonical.starts_with(root))
+    {
+        return Err(AppError::AccessDenied(format!(
+            "Access denied: {} is outside the allowed directories",
+            path
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn read_file_content(
+    path: String,
+    app: AppHandle,
+    scope: State<'_, FileAccessScope>,
+) -> Result<String, AppError> {
+    // Canonicalize first: resolves `..` segments and symlinks to their real target
+    let canonical = PathBuf::from(&path)
+        .canonicalize()
+        .map_err(|e| AppError::Io(format!("Failed to read file {}: {}", path, e)))?;
+    ensure_file_access(&canonical, &path, &app, &scope)?;
+
+    let file = fs::File::open(&canonical)
+        .map_err(|e| AppError::Io(format!("Failed to read file {}: {}", path, e)))?;
+    let mut bytes = Vec::new();
+    file.take(MAX_READ_BYTES)
+        .read_to_end(&mut bytes)
+        .map_err(|e| AppError::Io(format!("Failed to read file {}: {}", path, e)))?;
+
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        // Truncation may split a multi-byte character at the end; drop the partial tail
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            Ok(String::from_utf8(bytes).unwrap_or_default())
+        }
+        Err(e) => Err(AppError::InvalidInput(format!(
+            "Failed to read file {}: {}",
+            path, e
+        ))),
+    }
 }
 
+/// Show a file or folder selected in the OS file manager (same access rules as read_file_content)
 #[tauri::command]
-async fn read_file_content(path: String) -> Result<String, String> {
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file {}: {}", path, e))
+async fn reveal_in_explorer(
+    path: String,
+    app: AppHandle,
+    scope: State<'_, FileAccessScope>,
+) -> Result<(), AppError> {
+    let canonical = PathBuf::from(&path)
+        .canonicalize()
+        .map_err(|e| AppError::Io(format!("Failed to resolve {}: {}", path, e)))?;
+    ensure_file_access(&canonical, &path, &app, &scope)?;
+    reveal::reveal(&canonical)
 }
 
 // ============= AUTO-UPDATE COMMANDS =============
 
 #[tauri::command]
-async fn check_update(app: AppHandle) -> Result<Option<String>, String> {
+async fn check_update(app: AppHandle) -> Result<Option<String>, AppError> {
     match app.updater() {
-        Ok(updater) => {
-            match updater.check().await {
-                Ok(Some(update)) => Ok(Some(update.version)),
-                Ok(None) => Ok(None),
-                Err(e) => Err(format!("Update check failed: {}", e))
-            }
-        }
-        Err(e) => Err(format!("Updater not available: {}", e))
+        Ok(updater) => match updater.check().await {
+            Ok(Some(update)) => Ok(Some(update.version)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(AppError::Other(format!("Update check failed: {}", e))),
+        },
+        Err(e) => Err(AppError::Other(format!("Updater not available: {}", e))),
     }
 }
 
 #[tauri::command]
-async fn install_update(app: AppHandle) -> Result<(), String> {
+async fn install_update(app: AppHandle) -> Result<(), AppError> {
     match app.updater() {
-        Ok(updater) => {
-            match updater.check().await {
-                Ok(Some(update)) => {
-                    update.download_and_install(|_, _| {}, || {}).await
-                        .map_err(|e| format!("Update failed: {}", e))?;
-                    Ok(())
-                }
-                Ok(None) => Err("No update available".into()),
-                Err(e) => Err(format!("Update check failed: {}", e))
+        Ok(updater) => match updater.check().await {
+            Ok(Some(update)) => {
+                update
+                    .download_and_install(|_, _| {}, || {})
+                    .await
+                    .map_err(|e| format!("Update failed: {}", e))?;
+                Ok(())
             }
-        }
-        Err(e) => Err(format!("Updater not available: {}", e))
+            Ok(None) => Err(AppError::NotFound("No update available".to_string())),
+            Err(e) => Err(AppError::Other(format!("Update check failed: {}", e))),
+        },
+        Err(e) => Err(AppError::Other(format!("Updater not available: {}", e))),
+    }
+}
+
+#[derive(Serialize)]
+struct ProxySettings {
+    /// Proxy configured in the app
+    url: Option<String>,
+    /// Proxy inherited from HTTP_PROXY/HTTPS_PROXY, used when `url` is unset
+    env_url: Option<String>,
+}
+
+#[tauri::command]
+fn get_proxy() -> Result<ProxySettings, AppError> {
+    Ok(ProxySettings {
+        url: http::proxy_url(),
+        env_url: http::env_proxy_url(),
+    })
+}
+
+/// Set (or clear with `None`/empty) the proxy used for all outbound requests
+#[tauri::command]
+fn set_proxy(url: Option<String>, db: State<'_, DbState>) -> Result<(), AppError> {
+    let url = url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty());
+    if let Some(u) = &url {
+        http::build_proxy(u).map_err(AppError::InvalidInput)?;
     }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::set(&conn, settings::PROXY_URL, &serde_json::json!(url))?;
+    http::set_proxy_url(url);
+    Ok(())
+}
+
+/// Check connectivity through `url` (or the current setting) without saving it
+///
+/// Returns the HTTP status of the probe request.
+#[tauri::command]
+async fn test_proxy(url: Option<String>) -> Result<u16, AppError> {
+    const PROBE_URL: &str = "https://huggingface.co";
+    let url = url
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .or_else(http::proxy_url);
+    let client = http::client_builder_with(url.as_deref())
+        .map_err(AppError::InvalidInput)?
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client
+        .head(PROBE_URL)
+        .send()
+        .await
+        .map_err(|e| AppError::Other(format!("Proxy test failed: {}", e)))?;
+    Ok(resp.status().as_u16())
+}
+
+/// Whether closing or minimizing hides the window to the tray instead of quitting
+fn close_to_tray_enabled(app: &AppHandle) -> bool {
+    let Some(db) = app.try_state::<DbState>() else {
+        return false;
+    };
+    let Ok(conn) = db.0.lock() else {
+        return false;
+    };
+    settings::get_bool(&conn, settings::CLOSE_TO_TRAY)
 }
 
 fn main() {
@@ -344,56 +1006,144 @@ fn main() {
         .manage(DownloadManager {
             inner: Mutex::new(HashMap::new()),
         })
+        .manage(FileAccessScope(Mutex::new(HashSet::new())))
+        .manage(GenerationManager::default())
+        .manage(PromptDialogueState::default())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(|app| {
+            paths::init(app.handle())?;
+            paths::migrate_from_exe_dir();
+
             // Initialize database with proper app data directory
             let db_conn = db::init_db(app.handle()).expect("Failed to initialize database");
+            http::set_proxy_url(settings::get_string(&db_conn, settings::PROXY_URL));
+            let level = settings::get_f64(&db_conn, settings::WINDOW_OPACITY)
+                .unwrap_or(opacity::MAX_OPACITY)
+                .clamp(opacity::MIN_OPACITY, opacity::MAX_OPACITY);
             app.manage(DbState(Mutex::new(db_conn)));
+            // Restore a dimmed overlay (setup runs on the main thread)
+            if let Some(window) = app.get_webview_window("main") {
+                if level < opacity::MAX_OPACITY {
+                    let _ = opacity::apply_on_main_thread(&window.as_ref().window(), level);
+                }
+            }
+            tray::init(app)?;
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let WindowEvent::Destroyed = event {
+        .on_window_event(|window, event| match event {
+            WindowEvent::CloseRequested { api, .. }
+                if close_to_tray_enabled(window.app_handle()) =>
+            {
+                api.prevent_close();
+                let _ = window.hide();
+            }
+            WindowEvent::Resized(_)
+                if window.is_minimized().unwrap_or(false)
+                    && close_to_tray_enabled(window.app_handle()) =>
+            {
+                let _ = window.hide();
+            }
+            WindowEvent::Destroyed => {
                 // Stop server only when application is actually being destroyed
                 let _ = llama_install::stop_server_process(window.clone());
             }
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             system_info,
+            check_preset_feasibility,
             toggle_overlay,
             set_overlay_mode,
             apply_overlay_bounds,
+            center_overlay_on_active_monitor,
+            set_window_opacity,
+            get_window_opacity,
             set_click_through,
             start_llama,
             get_presets,
             import_pack,
             download_pack,
             download_status,
+            list_downloads,
+            list_download_states,
+            clear_download,
+            clear_finished_downloads,
             cancel_download,
+            pause_download,
+            resume_download,
+            check_download_space,
+            cancel_generation,
+            generate_completion,
+            emergency_stop,
             list_conversations,
             list_groups,
             create_conversation,
             get_conversation,
+            update_conversation,
+            auto_title_conversation,
+            export_conversation_json,
+            import_conversation_json,
+            export_conversations,
+            set_last_conversation,
+            get_last_conversation,
+            set_conversation_pinned,
+            set_conversation_strict,
             delete_conversation,
             list_messages,
             add_message,
             generate_text,
+            set_generation_timeout,
+            continue_generation,
+            regenerate_alternative,
+            list_variants,
+            set_active_variant,
             generate_prompt_ai_dialogue,
+            cancel_prompt_dialogue,
+            save_prompt_session,
+            list_prompt_sessions,
+            get_prompt_session,
+            list_prompt_templates,
+            set_prompt_template,
+            get_prompt_templates_dir,
             generate_prompt_ai,
             check_llama_server,
             health_check_llama_server,
+            get_server_props,
+            count_tokens,
+            get_running_server_info,
             download_llama_server,
+            install_server_from_zip,
+            get_server_release_info,
             start_llama_server,
             start_llama_for_conversation,
             start_llama_with_preset,
+            list_runnable_presets,
+            switch_to_preset,
             get_first_installed_preset,
+            scan_models,
             stop_llama_server,
             get_db_path_string,
+            get_setting,
+            set_setting,
+            list_settings,
+            get_all_settings,
             get_llama_logs,
             clear_llama_logs,
             get_server_diagnostics,
+            get_app_paths,
+            database_maintenance,
+            export_all,
+            import_all,
+            get_models_dir,
+            set_models_dir,
+            pick_file_to_read,
             read_file_content,
+            reveal_in_explorer,
+            get_proxy,
+            set_proxy,
+            test_proxy,
             // Update commands
             check_update,
             install_update
@@ -422,13 +1172,13 @@ async fn download_pack(
     args: DownloadArgs,
     dm: State<'_, DownloadManager>,
     app: AppHandle,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     const PACKS_JSON: &str = include_str!("../pack-sources.json");
     let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
     let pack = packs
         .into_iter()
         .find(|p| p.id == args.preset_id)
-        .ok_or_else(|| "Unknown preset".to_string())?;
+        .ok_or_else(|| AppError::UnknownPreset(format!("Unknown preset: {}", args.preset_id)))?;
     // Use models_root_dir for consistency across dev/prod
     let target_dir: PathBuf = models_root_dir(&app)?.join(&args.preset_id);
     let part_path = target_dir.join(format!("{}.part", pack.filename));
@@ -450,17 +1200,26 @@ async fn download_pack(
                         error: None,
                     },
                     cancel: Arc::new(AtomicBool::new(false)),
+                    pause: Arc::new(AtomicBool::new(false)),
+                    part_path: None,
                 },
             );
             return Ok("already_installed".into());
         } else {
-            return Err(
+            return Err(AppError::ModelNotFound(
                 "Local model file not found. Please place the model file manually.".to_string(),
-            );
+            ));
         }
     }
 
+    // Only the bytes still missing from a previous partial download count
+    if let Some(size) = pack.size_bytes {
+        let already = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+        disk::ensure_free_space(&target_dir, size.saturating_sub(already))?;
+    }
+
     let cancel_flag = Arc::new(AtomicBool::new(false));
+    let pause_flag = Arc::new(AtomicBool::new(false));
     {
         let mut map = dm.inner.lock().unwrap();
         map.insert(
@@ -474,6 +1233,8 @@ async fn download_pack(
                     error: None,
                 },
                 cancel: cancel_flag.clone(),
+                pause: pause_flag.clone(),
+                part_path: Some(part_path.clone()),
             },
         );
     }
@@ -481,32 +1242,114 @@ async fn download_pack(
     let preset_id = args.preset_id.clone();
     tokio::spawn(async move {
         let dm = app_handle.state::<DownloadManager>();
-        let _ = afs::create_dir_all(&target_dir).await;
-        let client = reqwest::Client::new();
+        if let Err(e) = afs::create_dir_all(&target_dir).await {
+            set_download_error(
+                &app_handle,
+                &preset_id,
+                format!("Failed to create {}: {}", target_dir.display(), e),
+            );
+            return;
+        }
+        let client = match http::client_builder().and_then(|b| b.build().map_err(|e| e.to_string()))
+        {
+            Ok(c) => c,
+            Err(e) => {
+                set_download_error(&app_handle, &preset_id, e);
+                return;
+            }
+        };
 
         let mut resume: u64 = 0;
         if let Ok(meta) = afs::metadata(&part_path).await {
             resume = meta.len();
         }
 
-        let mut req = client.get(&pack.url);
+        let send = |from: u64| {
+            let mut req = client.get(&pack.url);
+            if from > 0 {
+                req = req.header(reqwest::header::RANGE, format!("bytes={}-", from));
+            }
+            req.send()
+        };
+
+        let mut resp = match send(resume).await {
+            Ok(r) => r,
+            Err(e) => {
+                set_download_error(&app_handle, &preset_id, e.to_string());
+                return;
+            }
+        };
+
         if resume > 0 {
-            req = req.header(reqwest::header::RANGE, format!("bytes={}-", resume));
+            let status = resp.status();
+            let range = content_range(resp.headers());
+            match resume_decision(status, range, resume) {
+                ResumeDecision::Append => {}
+                ResumeDecision::Complete => {
+                    // An earlier run got every byte but stopped before moving the file
+                    {
+                        let mut map = dm.inner.lock().unwrap();
+                        if let Some(entry) = map.get_mut(&preset_id) {
+                            entry.state.written = resume;
+                        }
+                    }
+                    finish_download(
+                        &app_handle,
+                        &preset_id,
+                        &part_path,
+                        &final_path,
+                        Some(resume),
+                    )
+                    .await;
+                    return;
+                }
+                ResumeDecision::Refetch => {
+                    // Remote file changed under the .part, it can't be continued
+                    eprintln!(
+                        "[download_pack] Resume at {} rejected ({}), restarting {}",
+                        resume, status, preset_id
+                    );
+                    let _ = afs::remove_file(&part_path).await;
+                    resume = 0;
+                    resp = match send(0).await {
+                        Ok(r) => r,
+                        Err(e) => {
+                            set_download_error(&app_handle, &preset_id, e.to_string());
+                            return;
+                        }
+                    };
+                }
+                ResumeDecision::FromStart => {
+                    if status.is_success() {
+                        // Range ignored: this is the whole file, overwrite the .part
+                        eprintln!(
+                            "[download_pack] Server ignored Range for {}, restarting from 0",
+                            preset_id
+                        );
+                    }
+                    resume = 0;
+                }
+            }
         }
 
-        let resp = match req.send().await.and_then(|r| r.error_for_status()) {
+        let resp = match resp.error_for_status() {
             Ok(r) => r,
             Err(e) => {
-                let mut map = dm.inner.lock().unwrap();
-                if let Some(entry) = map.get_mut(&preset_id) {
-                    entry.state.status = "error".into();
-                    entry.state.error = Some(e.to_string());
+                // Retrying won't help a 4xx (bad URL, auth, gone), don't leave a .part to
+                // resume from. A 5xx or 429 is likely temporary, keep the progress.
+                let permanent = e.status().is_some_and(|status| {
+                    status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                });
+                if permanent {
+                    let _ = afs::remove_file(&part_path).await;
                 }
+                set_download_error(&app_handle, &preset_id, e.to_string());
                 return;
             }
         };
 
-        let total = resp.content_length().map(|cl| cl + resume);
+        let total = content_range_total(resp.headers())
+            .or_else(|| resp.content_length().map(|cl| cl + resume));
         {
             let mut map = dm.inner.lock().unwrap();
             if let Some(entry) = map.get_mut(&preset_id) {
@@ -516,16 +1359,16 @@ async fn download_pack(
         }
 
         let mut stream = resp.bytes_stream();
-        let mut file = if resume > 0 {
-            afs::OpenOptions::new()
-                .append(true)
-                .open(&part_path)
-                .await
-                .unwrap()
-        } else {
-            afs::File::create(&part_path).await.unwrap()
+        let mut file = match open_part_file(&part_path, resume).await {
+            Ok(f) => f,
+            Err(e) => {
+                set_download_error(&app_handle, &preset_id, e);
+                return;
+            }
         };
 
+        // Bytes received since free space was last checked
+        let mut unchecked: u64 = 0;
         while let Some(chunk) = stream.next().await {
             if cancel_flag.load(Ordering::SeqCst) {
                 let _ = afs::remove_file(&part_path).await;
@@ -535,78 +1378,663 @@ async fn download_pack(
                 }
                 return;
             }
+            if pause_flag.load(Ordering::SeqCst) {
+                // Only whole chunks were written, the .part is safe to resume from
+                let _ = file.flush().await;
+                let mut map = dm.inner.lock().unwrap();
+                if let Some(entry) = map.get_mut(&preset_id) {
+                    entry.state.status = "paused".into();
+                }
+                return;
+            }
             match chunk {
                 Ok(data) => {
-                    if file.write_all(&data).await.is_err() {
+                    if let Err(e) = file.write_all(&data).await {
+                        drop(file);
+                        if e.kind() == std::io::ErrorKind::StorageFull {
+                            // Appends only: what reached the disk is a valid prefix to resume from
+                            pause_for_low_disk(&app_handle, &preset_id, &target_dir);
+                            return;
+                        }
+                        // Partial write leaves the .part in an unknown state
+                        let _ = afs::remove_file(&part_path).await;
+                        set_download_error(&app_handle, &preset_id, format!("write failed: {}", e));
+                        return;
+                    }
+                    {
                         let mut map = dm.inner.lock().unwrap();
                         if let Some(entry) = map.get_mut(&preset_id) {
-                            entry.state.status = "error".into();
-                            entry.state.error = Some("write failed".into());
+                            entry.state.written += data.len() as u64;
                         }
-                        return;
                     }
-                    let mut map = dm.inner.lock().unwrap();
-                    if let Some(entry) = map.get_mut(&preset_id) {
-                        entry.state.written += data.len() as u64;
+                    unchecked += data.len() as u64;
+                    if unchecked >= LOW_DISK_CHECK_BYTES {
+                        unchecked = 0;
+                        let low = disk::available_space(&target_dir)
+                            .is_some_and(|free| free < disk::DISK_MARGIN_BYTES);
+                        if low {
+                            let _ = file.flush().await;
+                            pause_for_low_disk(&app_handle, &preset_id, &target_dir);
+                            return;
+                        }
                     }
                 }
                 Err(e) => {
-                    let mut map = dm.inner.lock().unwrap();
-                    if let Some(entry) = map.get_mut(&preset_id) {
-                        entry.state.status = "error".into();
-                        entry.state.error = Some(e.to_string());
-                    }
+                    set_download_error(&app_handle, &preset_id, e.to_string());
                     return;
                 }
             }
         }
 
-        let _ = file.flush().await;
-        let _ = afs::rename(&part_path, &final_path).await;
-        let mut map = dm.inner.lock().unwrap();
-        if let Some(entry) = map.get_mut(&preset_id) {
-            entry.state.status = "done".into();
-            entry.state.total = total;
+        if let Err(e) = file.flush().await {
+            drop(file);
+            if e.kind() == std::io::ErrorKind::StorageFull {
+                pause_for_low_disk(&app_handle, &preset_id, &target_dir);
+                return;
+            }
+            let _ = afs::remove_file(&part_path).await;
+            set_download_error(&app_handle, &preset_id, format!("flush failed: {}", e));
+            return;
         }
-        // Notify UI a model is now installed
-        let _ = app_handle.emit("model-installed", &preset_id);
+        // Close the handle before renaming (Windows refuses to move open files)
+        drop(file);
+        finish_download(&app_handle, &preset_id, &part_path, &final_path, total).await;
     });
 
     Ok("started".into())
 }
 
-#[tauri::command]
-async fn download_status(
-    preset_id: String,
-    dm: State<'_, DownloadManager>,
-) -> Result<DownloadState, String> {
-    let map = dm.inner.lock().unwrap();
-    if let Some(entry) = map.get(&preset_id) {
-        return Ok(entry.state.clone());
-    }
-    Err("not_found".into())
-}
+/// Open the `.part` to append to when resuming, or create (truncate) it otherwise
+async fn open_part_file(part_path: &Path, resume: u64) -> Result<afs::File, String> {
+    let opened = if resume > 0 {
+        afs::OpenOptions::new().append(true).open(part_path).await
+    } else {
+        afs::File::create(part_path).await
+    };
+    opened.map_err(|e| format!("Failed to open {}: {}", part_path.display(), e))
+}
+
+/// Check a fully downloaded `.part`, move it into place and mark the download done
+async fn finish_download(
+    app: &AppHandle,
+    preset_id: &str,
+    part_path: &Path,
+    final_path: &Path,
+    total: Option<u64>,
+) {
+    // A wrong URL or truncated transfer must not end up looking installed
+    if let Err(e) = gguf::validate_header(part_path) {
+        let _ = afs::remove_file(part_path).await;
+        set_download_error(app, preset_id, e);
+        return;
+    }
+    if let Err(e) = afs::rename(part_path, final_path).await {
+        set_download_error(
+            app,
+            preset_id,
+            format!("Failed to move {} into place: {}", part_path.display(), e),
+        );
+        return;
+    }
+    {
+        let dm = app.state::<DownloadManager>();
+        let mut map = dm.inner.lock().unwrap();
+        if let Some(entry) = map.get_mut(preset_id) {
+            entry.state.status = "done".into();
+            entry.state.total = total;
+        }
+    }
+    // Notify UI a model is now installed
+    let _ = app.emit("model-installed", preset_id);
+}
+
+/// Parse a `Content-Range` value into its start and total
+///
+/// `bytes <start>-<end>/<total>` gives both, the `bytes */<total>` of a 416 has
+/// no start, and a `*` total (unknown size) gives `None`.
+fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let total = match total.trim() {
+        "*" => None,
+        t => Some(t.parse().ok()?),
+    };
+    let start = match range.trim() {
+        "*" => None,
+        r => {
+            let (start, end) = r.split_once('-')?;
+            let start: u64 = start.trim().parse().ok()?;
+            let end: u64 = end.trim().parse().ok()?;
+            if end < start {
+                return None;
+            }
+            Some(start)
+        }
+    };
+    Some((start, total))
+}
+
+fn content_range(headers: &reqwest::header::HeaderMap) -> Option<(Option<u64>, Option<u64>)> {
+    parse_content_range(headers.get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?)
+}
+
+/// Full file size from a 206 response (`None` for `*`)
+fn content_range_total(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    content_range(headers).and_then(|(_, total)| total)
+}
+
+/// How to go on with a `.part` after requesting the bytes from `resume` on
+#[derive(Debug, PartialEq)]
+enum ResumeDecision {
+    /// The server resumed at the requested offset, append to the `.part`
+    Append,
+    /// The `.part` already holds the whole file
+    Complete,
+    /// The remote file changed, drop the `.part` and request it all again
+    Refetch,
+    /// The body starts at byte 0 (Range ignored), or is an error reported as usual
+    FromStart,
+}
+
+fn resume_decision(
+    status: reqwest::StatusCode,
+    range: Option<(Option<u64>, Option<u64>)>,
+    resume: u64,
+) -> ResumeDecision {
+    let (start, total) = range.unwrap_or((None, None));
+    match status {
+        reqwest::StatusCode::PARTIAL_CONTENT if start == Some(resume) => ResumeDecision::Append,
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE if total == Some(resume) => {
+            ResumeDecision::Complete
+        }
+        reqwest::StatusCode::PARTIAL_CONTENT | reqwest::StatusCode::RANGE_NOT_SATISFIABLE => {
+            ResumeDecision::Refetch
+        }
+        _ => ResumeDecision::FromStart,
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct ModelInstallFailed {
+    preset_id: String,
+    error: String,
+}
+
+/// Free space is checked every time this much has been downloaded
+const LOW_DISK_CHECK_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Serialize, Clone)]
+struct LowDiskSpace {
+    preset_id: String,
+    /// Free bytes on the models volume, `None` if it couldn't be read
+    available: Option<u64>,
+    /// Bytes still to download, `None` when the total size is unknown
+    remaining: Option<u64>,
+}
+
+/// Pause a download whose volume is (nearly) full and emit `low-disk-space`
+///
+/// The `.part` is kept, so `resume_download` continues once space is freed.
+fn pause_for_low_disk(app: &AppHandle, preset_id: &str, dir: &Path) {
+    let remaining = {
+        let dm = app.state::<DownloadManager>();
+        let mut map = dm.inner.lock().unwrap();
+        map.get_mut(preset_id).and_then(|entry| {
+            entry.state.status = "paused".into();
+            entry
+                .state
+                .total
+                .map(|t| t.saturating_sub(entry.state.written))
+        })
+    };
+    eprintln!(
+        "[download_pack] Low disk space in {}, pausing {}",
+        dir.display(),
+        preset_id
+    );
+    let _ = app.emit(
+        "low-disk-space",
+        LowDiskSpace {
+            preset_id: preset_id.to_string(),
+            available: disk::available_space(dir),
+            remaining,
+        },
+    );
+}
+
+/// Mark a download entry as failed and emit `model-install-failed` so the UI stops waiting on it
+fn set_download_error(app: &AppHandle, preset_id: &str, error: String) {
+    {
+        let dm = app.state::<DownloadManager>();
+        let mut map = dm.inner.lock().unwrap();
+        if let Some(entry) = map.get_mut(preset_id) {
+            entry.state.status = "error".into();
+            entry.state.error = Some(error.clone());
+        }
+    }
+    let _ = app.emit(
+        "model-install-failed",
+        ModelInstallFailed {
+            preset_id: preset_id.to_string(),
+            error,
+        },
+    );
+}
 
 #[tauri::command]
-async fn cancel_download(preset_id: String, dm: State<'_, DownloadManager>) -> Result<(), String> {
+async fn download_status(
+    preset_id: String,
+    dm: State<'_, DownloadManager>,
+) -> Result<DownloadState, AppError> {
     let map = dm.inner.lock().unwrap();
     if let Some(entry) = map.get(&preset_id) {
-        entry.cancel.store(true, Ordering::SeqCst);
+        return Ok(entry.state.clone());
+    }
+    Err(AppError::NotFound("not_found".to_string()))
+}
+
+/// Download state of every pack, keyed by preset id
+///
+/// Packs never downloaded this session are listed with status `idle`,
+/// along with any other tracked download (imported presets).
+#[tauri::command]
+async fn list_download_states(
+    dm: State<'_, DownloadManager>,
+) -> Result<HashMap<String, DownloadState>, AppError> {
+    const PACKS_JSON: &str = include_str!("../pack-sources.json");
+    let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
+    let mut states = dm.snapshot()?;
+    for pack in packs {
+        states.entry(pack.id).or_insert_with(|| DownloadState {
+            filename: pack.filename,
+            total: pack.size_bytes,
+            written: 0,
+            status: "idle".into(),
+            error: None,
+        });
+    }
+    Ok(states)
+}
+
+/// Every tracked download plus totals for a global progress indicator
+#[derive(Serialize)]
+struct DownloadsOverview {
+    downloads: HashMap<String, DownloadState>,
+    /// Sum of known sizes (downloads without a size yet are left out)
+    total_bytes: u64,
+    written_bytes: u64,
+    running: usize,
+    paused: usize,
+    done: usize,
+    error: usize,
+    canceled: usize,
+}
+
+/// All downloads, finished ones included until they are cleared
+#[tauri::command]
+async fn list_downloads(dm: State<'_, DownloadManager>) -> Result<DownloadsOverview, AppError> {
+    let downloads = dm.snapshot()?;
+    let mut overview = DownloadsOverview {
+        downloads: HashMap::new(),
+        total_bytes: 0,
+        written_bytes: 0,
+        running: 0,
+        paused: 0,
+        done: 0,
+        error: 0,
+        canceled: 0,
+    };
+    for state in downloads.values() {
+        overview.total_bytes += state.total.unwrap_or(0);
+        overview.written_bytes += state.written;
+        match state.status.as_str() {
+            "running" => overview.running += 1,
+            "paused" => overview.paused += 1,
+            "done" => overview.done += 1,
+            "error" => overview.error += 1,
+            "canceled" => overview.canceled += 1,
+            _ => {}
+        }
+    }
+    overview.downloads = downloads;
+    Ok(overview)
+}
+
+/// Abort a download and delete its `.part` (paused ones included)
+#[tauri::command]
+async fn cancel_download(
+    preset_id: String,
+    dm: State<'_, DownloadManager>,
+) -> Result<(), AppError> {
+    let mut map = dm.inner.lock().unwrap();
+    let entry = map
+        .get_mut(&preset_id)
+        .ok_or_else(|| AppError::NotFound("not_found".to_string()))?;
+    if entry.state.status == "paused" {
+        // No task left to notice the flag
+        if let Some(part_path) = &entry.part_path {
+            let _ = fs::remove_file(part_path);
+        }
+        entry.state.status = "canceled".into();
         return Ok(());
     }
-    Err("not_found".into())
+    entry.cancel.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Stop a running download but keep its `.part`, status becomes `paused`
+///
+/// Takes effect at the next received chunk.
+#[tauri::command]
+async fn pause_download(preset_id: String, dm: State<'_, DownloadManager>) -> Result<(), AppError> {
+    let map = dm.inner.lock().unwrap();
+    let entry = map
+        .get(&preset_id)
+        .ok_or_else(|| AppError::NotFound("not_found".to_string()))?;
+    if entry.state.status != "running" {
+        return Err(AppError::InvalidInput(format!(
+            "Download {} is {}, not running",
+            preset_id, entry.state.status
+        )));
+    }
+    entry.pause.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Restart a paused download from its `.part`
+#[tauri::command]
+async fn resume_download(
+    preset_id: String,
+    dm: State<'_, DownloadManager>,
+    app: AppHandle,
+) -> Result<String, AppError> {
+    {
+        let map = dm.inner.lock().unwrap();
+        let entry = map
+            .get(&preset_id)
+            .ok_or_else(|| AppError::NotFound("not_found".to_string()))?;
+        if entry.state.status != "paused" {
+            return Err(AppError::InvalidInput(format!(
+                "Download {} is {}, not paused",
+                preset_id, entry.state.status
+            )));
+        }
+    }
+    download_pack(DownloadArgs { preset_id }, dm, app).await
+}
+
+#[derive(Serialize)]
+struct DownloadSpace {
+    /// Free bytes on the models volume, `None` if it couldn't be read
+    available: Option<u64>,
+    /// Bytes still to download (size minus the `.part`), `None` when the size is unknown
+    remaining: Option<u64>,
+    /// Whether `download_pack`/`resume_download` would pass their space check
+    enough: bool,
+}
+
+/// Free space against what a preset still needs, e.g. after a `low-disk-space` pause
+#[tauri::command]
+async fn check_download_space(
+    preset_id: String,
+    app: AppHandle,
+) -> Result<DownloadSpace, AppError> {
+    const PACKS_JSON: &str = include_str!("../pack-sources.json");
+    let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
+    let pack = packs
+        .into_iter()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| AppError::UnknownPreset(format!("Unknown preset: {}", preset_id)))?;
+    let target_dir = models_root_dir(&app)?.join(&preset_id);
+    let already = fs::metadata(target_dir.join(format!("{}.part", pack.filename)))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let remaining = pack.size_bytes.map(|size| size.saturating_sub(already));
+    Ok(DownloadSpace {
+        available: disk::available_space(&target_dir),
+        remaining,
+        enough: disk::ensure_free_space(&target_dir, remaining.unwrap_or(0)).is_ok(),
+    })
+}
+
+/// Statuses whose entries are still in use and can't be cleared
+fn is_active_download(status: &str) -> bool {
+    status == "running" || status == "paused"
+}
+
+/// Remove one finished, failed or canceled download from the manager
+#[tauri::command]
+async fn clear_download(
+    preset_id: String,
+    dm: State<'_, DownloadManager>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    {
+        let mut map = dm.inner.lock().unwrap();
+        let entry = map
+            .get(&preset_id)
+            .ok_or_else(|| AppError::NotFound("not_found".to_string()))?;
+        if is_active_download(&entry.state.status) {
+            return Err(AppError::InvalidInput(format!(
+                "Download {} is still {}",
+                preset_id, entry.state.status
+            )));
+        }
+        map.remove(&preset_id);
+    }
+    let _ = app.emit("downloads-changed", ());
+    Ok(())
+}
+
+/// Remove every download that is no longer active, returns how many were cleared
+#[tauri::command]
+async fn clear_finished_downloads(
+    dm: State<'_, DownloadManager>,
+    app: AppHandle,
+) -> Result<usize, AppError> {
+    let removed = {
+        let mut map = dm.inner.lock().unwrap();
+        let before = map.len();
+        map.retain(|_, entry| is_active_download(&entry.state.status));
+        before - map.len()
+    };
+    if removed > 0 {
+        let _ = app.emit("downloads-changed", ());
+    }
+    Ok(removed)
 }
 
 #[tauri::command]
-async fn list_conversations(db: State<'_, DbState>) -> Result<Vec<db::Conversation>, String> {
+async fn list_conversations(db: State<'_, DbState>) -> Result<Vec<db::Conversation>, AppError> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    db::list_conversations(&conn).map_err(|e| e.to_string())
+    db::list_conversations(&conn).map_err(AppError::from)
+}
+
+#[derive(Deserialize)]
+struct UpdateConversationArgs {
+    #[serde(default)]
+    name: Option<String>,
+    /// Empty string removes the system prompt
+    #[serde(default, rename = "systemPrompt")]
+    system_prompt: Option<String>,
+    /// Replaces all parameters, including the generation timeout
+    #[serde(default)]
+    parameters: Option<ModelParameters>,
+}
+
+/// Edit a conversation; fields left out are kept as they are
+#[tauri::command]
+async fn update_conversation(
+    id: i64,
+    args: UpdateConversationArgs,
+    db: State<'_, DbState>,
+) -> Result<db::Conversation, AppError> {
+    let name = args.name.as_deref().map(str::trim);
+    if name.is_some_and(str::is_empty) {
+        return Err(AppError::InvalidInput(
+            "Conversation name is empty".to_string(),
+        ));
+    }
+    if let Some(p) = &args.parameters {
+        p.validate()?;
+    }
+    let params = args.parameters.map(|p| db::ConversationParamsUpdate {
+        temperature: p.temperature,
+        top_p: p.top_p,
+        max_tokens: p.max_tokens,
+        repeat_penalty: p.repeat_penalty,
+        generation_timeout_secs: p.generation_timeout_secs,
+        presence_penalty: p.presence_penalty,
+        frequency_penalty: p.frequency_penalty,
+        top_k: p.top_k,
+        min_p: p.min_p,
+    });
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::update_conversation(
+        &mut conn,
+        id,
+        name,
+        args.system_prompt.as_deref(),
+        params.as_ref(),
+    )?;
+    db::get_conversation(&conn, id).map_err(AppError::from)
+}
+
+/// Longest title kept from the model's answer, in characters
+const AUTO_TITLE_MAX_CHARS: usize = 60;
+
+/// First line of a title reply, without quotes, "Title:" prefixes or trailing dots
+fn clean_title(reply: &str) -> String {
+    let line = reply
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or_default();
+    let line = match line.split_once(':') {
+        Some((label, rest))
+            if label.trim().eq_ignore_ascii_case("title")
+                || label.trim().eq_ignore_ascii_case("titre") =>
+        {
+            rest
+        }
+        _ => line,
+    };
+    let quote = |c: char| matches!(c, '"' | '\'' | '`' | '*' | '#' | '«' | '»' | '“' | '”');
+    let title = line
+        .trim()
+        .trim_start_matches(quote)
+        .trim_end_matches(|c: char| quote(c) || c == '.')
+        .trim();
+    title.chars().take(AUTO_TITLE_MAX_CHARS).collect()
+}
+
+/// Name a conversation from its first exchange, using the running llama-server
+///
+/// Only renames while the name is still `placeholder_name` (the default the
+/// UI gave it); returns `None` when skipped, the renamed conversation otherwise.
+#[tauri::command]
+async fn auto_title_conversation(
+    conversation_id: i64,
+    placeholder_name: String,
+    db: State<'_, DbState>,
+) -> Result<Option<db::Conversation>, AppError> {
+    let (conversation, messages) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            db::get_conversation(&conn, conversation_id)?,
+            db::list_messages(&conn, conversation_id)?,
+        )
+    };
+    let placeholder = placeholder_name.trim();
+    if conversation.name.trim() != placeholder {
+        return Ok(None);
+    }
+    let first = |role: &str| {
+        messages
+            .iter()
+            .find(|m| m.role == role && !m.content.trim().is_empty())
+            .map(|m| m.content.chars().take(1000).collect::<String>())
+    };
+    let (Some(question), Some(answer)) = (first("user"), first("assistant")) else {
+        return Ok(None);
+    };
+
+    let language = lang::prompt_language(None, &question);
+    let payload = llama::ChatCompletionRequest {
+        model: conversation.preset_id.clone(),
+        messages: vec![
+            llama::ChatMessage {
+                role: "system".into(),
+                content: format!(
+                    "Give a 3-5 word title for this chat, in {}. Reply with the title only, no quotes.",
+                    language
+                ),
+            },
+            llama::ChatMessage {
+                role: "user".into(),
+                content: format!("User: {}\n\nAssistant: {}", question, answer),
+            },
+        ],
+        stream: false,
+        temperature: 0.3,
+        top_p: 0.9,
+        max_tokens: 24,
+        repeat_penalty: 1.1,
+        presence_penalty: None,
+        frequency_penalty: None,
+        top_k: None,
+        min_p: None,
+        response_format: None,
+        grammar: None,
+        seed: None,
+    };
+
+    let client = http::client_builder()?
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client
+        .post(format!("{}/v1/chat/completions", llama::get_server_url()))
+        .json(&payload)
+        .send()
+        .await
+        .map_err(llama_request_error)?;
+    if !resp.status().is_success() {
+        return Err(AppError::ServerError(format!(
+            "llama-server returned error: {}",
+            resp.status()
+        )));
+    }
+    let parsed: ChatResp = resp
+        .json()
+        .await
+        .map_err(|e| AppError::ServerError(format!("Invalid response: {}", e)))?;
+    let title = parsed
+        .choices
+        .first()
+        .map(|c| clean_title(&c.message.content))
+        .unwrap_or_default();
+    if title.is_empty() {
+        return Err(AppError::InvalidOutput(
+            "Model returned an empty title".to_string(),
+        ));
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    // The user may have renamed it while the title was being generated
+    let current = db::get_conversation(&conn, conversation_id)?;
+    if current.name.trim() != placeholder {
+        return Ok(None);
+    }
+    db::update_conversation(&mut conn, conversation_id, Some(&title), None, None)?;
+    db::get_conversation(&conn, conversation_id)
+        .map(Some)
+        .map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn list_groups(db: State<'_, DbState>) -> Result<Vec<db::Group>, String> {
+async fn list_groups(db: State<'_, DbState>) -> Result<Vec<db::Group>, AppError> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    db::list_groups(&conn).map_err(|e| e.to_string())
+    db::list_groups(&conn).map_err(AppError::from)
 }
 
 #[derive(Deserialize)]
@@ -618,6 +2046,48 @@ struct ModelParameters {
     max_tokens: i32,
     #[serde(rename = "repeatPenalty")]
     repeat_penalty: f32,
+    #[serde(default, rename = "generationTimeoutSecs")]
+    generation_timeout_secs: Option<i64>,
+    #[serde(default, rename = "presencePenalty")]
+    presence_penalty: Option<f32>,
+    #[serde(default, rename = "frequencyPenalty")]
+    frequency_penalty: Option<f32>,
+    #[serde(default, rename = "topK")]
+    top_k: Option<i32>,
+    #[serde(default, rename = "minP")]
+    min_p: Option<f32>,
+}
+
+impl ModelParameters {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_sampling(self.top_k, self.min_p)?;
+        validate_timeout(self.generation_timeout_secs)
+    }
+}
+
+/// A generation timeout must be positive, `None` means the default
+fn validate_timeout(timeout_secs: Option<i64>) -> Result<(), AppError> {
+    if matches!(timeout_secs, Some(s) if s <= 0) {
+        return Err(AppError::InvalidInput(
+            "Timeout must be a positive number of seconds".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Range checks shared by every command taking optional sampling params
+fn validate_sampling(top_k: Option<i32>, min_p: Option<f32>) -> Result<(), AppError> {
+    if matches!(top_k, Some(k) if k < 0) {
+        return Err(AppError::InvalidInput(
+            "top_k must be 0 or greater".to_string(),
+        ));
+    }
+    if matches!(min_p, Some(p) if !(0.0..=1.0).contains(&p)) {
+        return Err(AppError::InvalidInput(
+            "min_p must be between 0 and 1".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 #[derive(Deserialize)]
@@ -630,13 +2100,18 @@ struct CreateConversationArgs {
     #[serde(rename = "systemPrompt")]
     system_prompt: String,
     parameters: ModelParameters,
+    /// Defaults to the `strict_mode` setting
+    #[serde(default)]
+    strict: Option<bool>,
 }
 
 #[tauri::command]
 async fn create_conversation(
     args: CreateConversationArgs,
     db: State<'_, DbState>,
-) -> Result<i64, String> {
+) -> Result<i64, AppError> {
+    args.parameters.validate()?;
+
     // Scope lock to avoid holding across awaits
     let conversation_id = {
         let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -645,11 +2120,11 @@ async fn create_conversation(
         let group_id = if let Some(group_name) = &args.group_name {
             if !group_name.is_empty() {
                 // Try to find existing group or create new one
-                let groups = db::list_groups(&conn).map_err(|e| e.to_string())?;
+                let groups = db::list_groups(&conn)?;
                 if let Some(group) = groups.iter().find(|g| g.name == *group_name) {
                     Some(group.id)
                 } else {
-                    Some(db::create_group(&conn, group_name).map_err(|e| e.to_string())?)
+                    Some(db::create_group(&conn, group_name)?)
                 }
             } else {
                 None
@@ -674,9 +2149,17 @@ async fn create_conversation(
             max_tokens: args.parameters.max_tokens,
             repeat_penalty: args.parameters.repeat_penalty,
             dataset_ids: None, // RAG removed
+            generation_timeout_secs: args.parameters.generation_timeout_secs,
+            presence_penalty: args.parameters.presence_penalty,
+            frequency_penalty: args.parameters.frequency_penalty,
+            top_k: args.parameters.top_k,
+            min_p: args.parameters.min_p,
+            strict: args
+                .strict
+                .unwrap_or_else(|| settings::get_bool(&conn, settings::STRICT_MODE)),
         };
 
-        db::create_conversation(&conn, params).map_err(|e| e.to_string())?
+        db::create_conversation(&conn, params)?
     };
 
     // Dataset linking removed (RAG system deprecated)
@@ -685,75 +2168,311 @@ async fn create_conversation(
 }
 
 #[tauri::command]
-async fn get_conversation(id: i64, db: State<'_, DbState>) -> Result<db::Conversation, String> {
+async fn get_conversation(id: i64, db: State<'_, DbState>) -> Result<db::Conversation, AppError> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    db::get_conversation(&conn, id).map_err(|e| e.to_string())
+    db::get_conversation(&conn, id).map_err(AppError::from)
 }
 
+/// Serialize a conversation and its messages into a versioned JSON document
 #[tauri::command]
-async fn delete_conversation(id: i64, db: State<'_, DbState>) -> Result<(), String> {
+async fn export_conversation_json(id: i64, db: State<'_, DbState>) -> Result<String, AppError> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    db::delete_conversation(&conn, id).map_err(|e| e.to_string())
+    let doc = export::export_conversation(&conn, id)?;
+    Ok(serde_json::to_string_pretty(&doc)?)
 }
 
-#[tauri::command]
-async fn list_messages(
-    conversation_id: i64,
-    db: State<'_, DbState>,
-) -> Result<Vec<db::Message>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    db::list_messages(&conn, conversation_id).map_err(|e| e.to_string())
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Json,
+    Markdown,
 }
 
+/// Write each conversation to its own file in `dest_dir`, returns the written paths
+///
+/// Files are named after the conversations; the id (and a counter if needed)
+/// is appended when two names clash or a file with that name already exists.
 #[tauri::command]
-fn get_db_path_string(app: tauri::AppHandle) -> Result<String, String> {
-    let p = crate::db::get_db_path(&app)?;
-    Ok(p.to_string_lossy().to_string())
+async fn export_conversations(
+    ids: Vec<i64>,
+    dest_dir: String,
+    format: ExportFormat,
+    db: State<'_, DbState>,
+) -> Result<Vec<String>, AppError> {
+    let dir = PathBuf::from(&dest_dir);
+    if !dir.is_dir() {
+        return Err(AppError::InvalidInput(format!(
+            "{} is not a folder",
+            dest_dir
+        )));
+    }
+    // Every id is checked before anything is written
+    let docs = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        ids.iter()
+            .map(|id| export::export_conversation(&conn, *id).map(|doc| (*id, doc)))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let extension = match format {
+        ExportFormat::Json => "json",
+        ExportFormat::Markdown => "md",
+    };
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (id, doc) in docs {
+        let content = match format {
+            ExportFormat::Json => serde_json::to_string_pretty(&doc)?,
+            ExportFormat::Markdown => export::to_markdown(&doc),
+        };
+        let stem = export::file_stem(&doc.conversation.name);
+        let (path, mut file) = export::create_export_file(&dir, &stem, id, extension)
+            .map_err(|e| AppError::Io(format!("Failed to create a file in {}: {}", dest_dir, e)))?;
+        file.write_all(content.as_bytes())
+            .map_err(|e| AppError::Io(format!("Failed to write {}: {}", path.display(), e)))?;
+        written.push(path);
+    }
+    Ok(written
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect())
 }
 
+/// Recreate a conversation from `export_conversation_json` output, returns the new id
 #[tauri::command]
-async fn add_message(
-    conversation_id: i64,
-    role: String,
-    content: String,
-    db: State<'_, DbState>,
-) -> Result<i64, String> {
+async fn import_conversation_json(json: String, db: State<'_, DbState>) -> Result<i64, AppError> {
+    let doc: export::ConversationExport = serde_json::from_str(&json)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid conversation export: {}", e)))?;
     let mut conn = db.0.lock().map_err(|e| e.to_string())?;
-    db::add_message(&mut conn, conversation_id, &role, &content).map_err(|e| e.to_string())
+    export::import_conversation(&mut conn, doc)
 }
 
+/// Remember the conversation the user just opened, to reopen it on next launch
+#[tauri::command]
+async fn set_last_conversation(id: i64, db: State<'_, DbState>) -> Result<(), AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::set(
+        &conn,
+        settings::LAST_CONVERSATION_ID,
+        &serde_json::json!(id),
+    )
+    .map_err(AppError::from)
+}
 
-
+/// Last opened conversation, `None` if none was recorded or it has been deleted
 #[tauri::command]
-async fn generate_text(
-    conversation_id: i64,
-    user_message: String,
-    window: Window,
+async fn get_last_conversation(
     db: State<'_, DbState>,
-) -> Result<(), String> {
-    // Load conversation
-    let conversation = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        db::get_conversation(&conn, conversation_id).map_err(|e| e.to_string())?
+) -> Result<Option<db::Conversation>, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let Some(id) = settings::get_i64(&conn, settings::LAST_CONVERSATION_ID) else {
+        return Ok(None);
     };
+    match db::get_conversation(&conn, id) {
+        Ok(conv) => Ok(Some(conv)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    // Load message history
-    let messages = {
-        let conn = db.0.lock().map_err(|e| e.to_string())?;
-        db::list_messages(&conn, conversation_id).map_err(|e| e.to_string())?
-    };
+/// Keep a conversation at the top of the list (or release it)
+#[tauri::command]
+async fn set_conversation_pinned(
+    id: i64,
+    pinned: bool,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::set_conversation_pinned(&conn, id, pinned).map_err(AppError::from)
+}
+
+/// Add (or stop adding) the strict rules to a conversation's system prompt
+#[tauri::command]
+async fn set_conversation_strict(
+    id: i64,
+    strict: bool,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::set_conversation_strict(&conn, id, strict).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn delete_conversation(id: i64, db: State<'_, DbState>) -> Result<(), AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::delete_conversation(&conn, id).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn list_messages(
+    conversation_id: i64,
+    db: State<'_, DbState>,
+) -> Result<Vec<db::Message>, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::list_messages(&conn, conversation_id).map_err(AppError::from)
+}
+
+/// Read an app-wide preference
+///
+/// Known keys fall back to their default, unknown keys that were never set give `None`.
+#[tauri::command]
+async fn get_setting(
+    key: String,
+    db: State<'_, DbState>,
+) -> Result<Option<serde_json::Value>, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::get(&conn, &key).map_err(AppError::from)
+}
+
+/// Store an app-wide preference as JSON
+///
+/// Keys with their own command (proxy, models folder, ...) are rejected.
+#[tauri::command]
+async fn set_setting(
+    key: String,
+    value: serde_json::Value,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    if key.trim().is_empty() {
+        return Err(AppError::InvalidInput("Setting key is empty".to_string()));
+    }
+    if let Some(command) = settings::dedicated_setter(&key) {
+        return Err(AppError::InvalidInput(format!(
+            "Setting {} can only be changed with {}",
+            key, command
+        )));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::set(&conn, &key, &value).map_err(AppError::from)
+}
+
+/// Every known setting with its default, plus anything else stored
+#[tauri::command]
+async fn list_settings(
+    db: State<'_, DbState>,
+) -> Result<HashMap<String, serde_json::Value>, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::list(&conn).map_err(AppError::from)
+}
+
+/// Former name of `list_settings`, kept for existing callers
+#[tauri::command]
+async fn get_all_settings(
+    db: State<'_, DbState>,
+) -> Result<HashMap<String, serde_json::Value>, AppError> {
+    list_settings(db).await
+}
+
+#[tauri::command]
+fn get_db_path_string(app: tauri::AppHandle) -> Result<String, AppError> {
+    let p = crate::db::get_db_path(&app)?;
+    Ok(p.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+async fn add_message(
+    conversation_id: i64,
+    role: String,
+    content: String,
+    db: State<'_, DbState>,
+) -> Result<i64, AppError> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::add_message(&mut conn, conversation_id, &role, &content).map_err(AppError::from)
+}
+
+/// Classify a failed request to llama-server (unreachable vs. other failure)
+fn llama_request_error(e: reqwest::Error) -> AppError {
+    if e.is_connect() {
+        AppError::ServerNotRunning(
+            "llama-server is not running. Please start it first.".to_string(),
+        )
+    } else {
+        AppError::ServerError(format!("Failed to connect to llama-server: {}", e))
+    }
+}
+
+/// Idle timeout used when a conversation doesn't set `generation_timeout_secs`
+const DEFAULT_GENERATION_TIMEOUT_SECS: u64 = 120;
+
+/// Set how long generation may go without receiving data (None restores the default)
+#[tauri::command]
+async fn set_generation_timeout(
+    conversation_id: i64,
+    timeout_secs: Option<i64>,
+    db: State<'_, DbState>,
+) -> Result<(), AppError> {
+    validate_timeout(timeout_secs)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::set_generation_timeout(&conn, conversation_id, timeout_secs).map_err(AppError::from)
+}
+
+/// Payload of every `generation-*` event, so the UI can route it to the right bubble
+#[derive(Serialize, Clone)]
+struct GenerationEvent {
+    request_id: String,
+    conversation_id: i64,
+    content: String,
+    /// Malformed SSE chunks dropped during the stream, only on the terminal event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped_chunks: Option<usize>,
+    /// Times the stream was reconnected after dropping, only on the terminal event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reconnects: Option<u32>,
+}
+
+/// Payload of `<event_prefix>-parse-error`
+#[derive(Serialize, Clone)]
+struct SseParseErrorEvent {
+    line: String,
+    error: String,
+}
+
+/// Finish reasons after which `continue_generation` may extend the message
+const RESUMABLE_FINISH_REASONS: [&str; 4] = ["length", "timeout", "interrupted", "canceled"];
+
+/// Nudge sent after a cut-off assistant turn to get the rest of it
+const CONTINUE_PROMPT: &str =
+    "Continue exactly where you left off. Do not repeat what you already wrote.";
+
+/// Result of streaming one chat completion
+struct StreamOutcome {
+    text: String,
+    /// `stop`, `length`, `timeout`, `canceled`, or `interrupted` if the stream ended without one
+    finish_reason: String,
+    /// Number of SSE chunks that couldn't be parsed and were skipped
+    skipped_chunks: usize,
+    /// Times the stream dropped and was resumed (see `stream_with_reconnect`)
+    reconnects: u32,
+}
+
+fn idle_timeout_secs(conversation: &db::Conversation) -> u64 {
+    conversation
+        .generation_timeout_secs
+        .filter(|s| *s > 0)
+        .map(|s| s as u64)
+        .unwrap_or(DEFAULT_GENERATION_TIMEOUT_SECS)
+}
 
-    // Build chat messages
+/// System prompt (after the strict rules, if any) followed by the stored history
+fn history_chat_messages(
+    conversation: &db::Conversation,
+    messages: Vec<db::Message>,
+    strict_rules: Option<&str>,
+) -> Vec<llama::ChatMessage> {
     let mut chat_messages = Vec::new();
 
     // Add system prompt if exists
-    if let Some(system_prompt) = &conversation.system_prompt {
-        if !system_prompt.is_empty() {
-            chat_messages.push(llama::ChatMessage {
-                role: "system".to_string(),
-                content: system_prompt.clone(),
-            });
-        }
+    let system_prompt = [strict_rules, conversation.system_prompt.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if !system_prompt.is_empty() {
+        chat_messages.push(llama::ChatMessage {
+            role: "system".to_string(),
+            content: system_prompt,
+        });
     }
 
     // Add message history
@@ -764,158 +2483,1078 @@ async fn generate_text(
         });
     }
 
-    // Add new user message
-    chat_messages.push(llama::ChatMessage {
-        role: "user".to_string(),
-        content: user_message,
-    });
+    chat_messages
+}
 
-    // Build payload
-    let payload = llama::ChatCompletionRequest {
+fn chat_payload(
+    conversation: &db::Conversation,
+    messages: Vec<llama::ChatMessage>,
+) -> llama::ChatCompletionRequest {
+    llama::ChatCompletionRequest {
         model: conversation.preset_id.clone(),
-        messages: chat_messages,
+        messages,
         stream: true,
         temperature: conversation.temperature,
         top_p: conversation.top_p,
         max_tokens: conversation.max_tokens,
         repeat_penalty: conversation.repeat_penalty,
+        presence_penalty: conversation.presence_penalty,
+        frequency_penalty: conversation.frequency_penalty,
+        top_k: conversation.top_k,
+        min_p: conversation.min_p,
+        response_format: None,
+        grammar: None,
+        seed: None,
+    }
+}
+
+/// What gets stored with an assistant message generated from `payload`
+///
+/// A reply stitched across a reconnect can't be reproduced, so it keeps no seed.
+fn generation_params(
+    payload: &llama::ChatCompletionRequest,
+    outcome: &StreamOutcome,
+) -> db::GenerationParams {
+    db::GenerationParams {
+        model: payload.model.clone(),
+        temperature: payload.temperature,
+        top_p: payload.top_p,
+        max_tokens: payload.max_tokens,
+        repeat_penalty: payload.repeat_penalty,
+        presence_penalty: payload.presence_penalty,
+        frequency_penalty: payload.frequency_penalty,
+        top_k: payload.top_k,
+        min_p: payload.min_p,
+        seed: payload.seed.filter(|_| outcome.reconnects == 0),
+    }
+}
+
+/// Reject a non-streamed reply that should have been JSON but isn't
+fn validate_structured_output(
+    payload: &llama::ChatCompletionRequest,
+    content: &str,
+) -> Result<(), AppError> {
+    if !payload.expects_json() {
+        return Ok(());
+    }
+    serde_json::from_str::<serde_json::Value>(content)
+        .map(|_| ())
+        .map_err(|e| AppError::InvalidOutput(format!("Model did not return valid JSON: {}", e)))
+}
+
+/// `stream_completion` on `/v1/chat/completions`
+async fn stream_chat_completion<F, P>(
+    payload: &llama::ChatCompletionRequest,
+    idle_timeout: u64,
+    cancel: &AtomicBool,
+    window: &Window,
+    event_prefix: &str,
+    event: F,
+) -> Result<StreamOutcome, AppError>
+where
+    F: Fn(&str) -> P,
+    P: Serialize + Clone,
+{
+    eprintln!(
+        "[{}] Parameters: temp={}, top_p={}, max_tokens={}, repeat_penalty={}, presence_penalty={:?}, frequency_penalty={:?}, top_k={:?}, min_p={:?}",
+        event_prefix,
+        payload.temperature,
+        payload.top_p,
+        payload.max_tokens,
+        payload.repeat_penalty,
+        payload.presence_penalty,
+        payload.frequency_penalty,
+        payload.top_k,
+        payload.min_p
+    );
+    stream_completion(
+        "/v1/chat/completions",
+        payload,
+        idle_timeout,
+        cancel,
+        window,
+        event_prefix,
+        event,
+    )
+    .await
+}
+
+/// Stream a completion from `endpoint`, emitting `<event_prefix>-chunk` for every delta
+///
+/// Malformed chunks are skipped and reported through `<event_prefix>-parse-error`.
+/// Setting `cancel` stops reading after the current chunk (finish reason `canceled`).
+///
+/// The idle timeout resets on every received chunk, so slow but steady
+/// streams survive. Going idle is not an error: the partial text comes back
+/// with finish reason `timeout`.
+async fn stream_completion<T, F, P>(
+    endpoint: &str,
+    payload: &T,
+    idle_timeout: u64,
+    cancel: &AtomicBool,
+    window: &Window,
+    event_prefix: &str,
+    event: F,
+) -> Result<StreamOutcome, AppError>
+where
+    T: Serialize,
+    F: Fn(&str) -> P,
+    P: Serialize + Clone,
+{
+    let chunk_event = format!("{}-chunk", event_prefix);
+    let parse_error_event = format!("{}-parse-error", event_prefix);
+
+    // Send request to llama-server
+    let server_url = llama::get_server_url();
+    let client = http::client_builder()?
+        .connect_timeout(std::time::Duration::from_secs(10))
+        .read_timeout(std::time::Duration::from_secs(idle_timeout))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let response = match client
+        .post(format!("{}{}", server_url, endpoint))
+        .json(payload)
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(e) if e.is_timeout() => {
+            return Ok(StreamOutcome {
+                text: String::new(),
+                finish_reason: "timeout".to_string(),
+                skipped_chunks: 0,
+                reconnects: 0,
+            });
+        }
+        Err(e) => return Err(llama_request_error(e)),
+    };
+
+    if !response.status().is_success() {
+        let error_msg = format!("llama-server returned error: {}", response.status());
+        window
+            .emit(&format!("{}-error", event_prefix), event(&error_msg))
+            .ok();
+        return Err(AppError::ServerError(error_msg));
+    }
+
+    // Stream response
+    let mut stream = response.bytes_stream();
+    let mut decoder = sse::SseDecoder::default();
+    let mut accumulated = String::new();
+    let mut finished = false;
+    let mut finish_reason: Option<String> = None;
+    let mut timed_out = false;
+    let mut canceled = false;
+    let mut skipped_chunks = 0usize;
+
+    println!("[generate_text] Starting to stream response...");
+
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            canceled = true;
+            break;
+        }
+        let (events, ended) = match stream.next().await {
+            Some(Ok(bytes)) => (decoder.push(&bytes), false),
+            Some(Err(e)) if e.is_timeout() => {
+                println!(
+                    "[generate_text] No data for {}s, keeping partial response",
+                    idle_timeout
+                );
+                timed_out = true;
+                break;
+            }
+            // Stopping the server to cancel drops the connection
+            Some(Err(_)) if cancel.load(Ordering::SeqCst) => {
+                canceled = true;
+                break;
+            }
+            // Dropped mid-answer, keep what we have (finish reason `interrupted`)
+            Some(Err(e)) => {
+                eprintln!("[generate_text] Stream broken: {}", e);
+                break;
+            }
+            None => (decoder.finish().into_iter().collect(), true),
+        };
+
+        for data in events {
+            println!("[generate_text] SSE data: {}", data);
+
+            if data == "[DONE]" {
+                println!("[generate_text] Received [DONE], finishing stream");
+                finished = true;
+                break;
+            }
+
+            // Parse SSE chunk
+            match serde_json::from_str::<llama::SSEChunk>(&data) {
+                Ok(sse_chunk) => {
+                    if let Some(choice) = sse_chunk.choices.first() {
+                        // Extract content delta
+                        if let Some(content) = choice.content() {
+                            if !content.is_empty() {
+                                accumulated.push_str(content);
+                                println!("[generate_text] Emitting chunk: {}", content);
+                                // Emit chunk to frontend
+                                if let Err(e) = window.emit(&chunk_event, event(content)) {
+                                    println!("[generate_text] Failed to emit chunk: {:?}", e);
+                                }
+                            }
+                        }
+
+                        // Check if generation is complete
+                        if let Some(reason) = &choice.finish_reason {
+                            if reason == "stop" || reason == "length" {
+                                println!("[generate_text] Finish reason: {}", reason);
+                                finish_reason = Some(reason.clone());
+                                finished = true;
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    // The server gave up mid-stream, whatever came before is not a usable answer
+                    if let Some(message) = llama::stream_error_message(&data) {
+                        eprintln!("[generate_text] Server error in stream: {}", message);
+                        let error_msg = format!("llama-server error: {}", message);
+                        window
+                            .emit(&format!("{}-error", event_prefix), event(&error_msg))
+                            .ok();
+                        return Err(AppError::ServerError(error_msg));
+                    }
+                    eprintln!("[generate_text] ⚠️ PARSE ERROR: {} | JSON: {}", e, data);
+                    eprintln!("[generate_text] ⚠️ This chunk was SKIPPED. Check if llama-server is sending malformed JSON.");
+                    // Report it but keep going, one bad chunk shouldn't abort the answer
+                    skipped_chunks += 1;
+                    window
+                        .emit(
+                            &parse_error_event,
+                            SseParseErrorEvent {
+                                line: data.clone(),
+                                error: e.to_string(),
+                            },
+                        )
+                        .ok();
+                }
+            }
+        }
+
+        // If the stream indicated completion, exit the outer loop promptly
+        if finished || ended {
+            break;
+        }
+    }
+
+    println!(
+        "[generate_text] Streaming complete. Total accumulated: {} chars",
+        accumulated.len()
+    );
+
+    let finish_reason = if canceled {
+        "canceled".to_string()
+    } else if timed_out {
+        "timeout".to_string()
+    } else if let Some(reason) = finish_reason {
+        reason
+    } else if finished {
+        "stop".to_string()
+    } else {
+        // Connection closed before [DONE] or a finish reason
+        "interrupted".to_string()
+    };
+
+    Ok(StreamOutcome {
+        text: accumulated,
+        finish_reason,
+        skipped_chunks,
+        reconnects: 0,
+    })
+}
+
+/// Reconnection attempts after a stream drops mid-answer
+const MAX_STREAM_RECONNECTS: u32 = 1;
+
+/// `stream_chat_completion`, retrying when the stream drops mid-answer
+///
+/// The retry sends the text received so far as an assistant prefix so the
+/// model continues it; the continuation is stitched onto the first part.
+/// Emits `<event_prefix>-reconnect` before each attempt. A failing retry
+/// keeps what was already received. Seeded requests are never retried, a
+/// stitched answer wouldn't be what the seed reproduces.
+async fn stream_with_reconnect<F, P>(
+    payload: &llama::ChatCompletionRequest,
+    idle_timeout: u64,
+    cancel: &AtomicBool,
+    window: &Window,
+    event_prefix: &str,
+    event: F,
+) -> Result<StreamOutcome, AppError>
+where
+    F: Fn(&str) -> P,
+    P: Serialize + Clone,
+{
+    let mut outcome =
+        stream_chat_completion(payload, idle_timeout, cancel, window, event_prefix, &event).await?;
+    while outcome.finish_reason == "interrupted"
+        && outcome.reconnects < MAX_STREAM_RECONNECTS
+        && payload.seed.is_none()
+    {
+        outcome.reconnects += 1;
+        println!(
+            "[generate_text] Stream dropped after {} chars, reconnecting",
+            outcome.text.len()
+        );
+        window
+            .emit(&format!("{}-reconnect", event_prefix), event(&outcome.text))
+            .ok();
+
+        let mut retry = payload.clone();
+        if !outcome.text.is_empty() {
+            retry.messages.push(llama::ChatMessage {
+                role: "assistant".to_string(),
+                content: outcome.text.clone(),
+            });
+        }
+        match stream_chat_completion(&retry, idle_timeout, cancel, window, event_prefix, &event)
+            .await
+        {
+            Ok(next) => {
+                outcome.text.push_str(&next.text);
+                outcome.finish_reason = next.finish_reason;
+                outcome.skipped_chunks += next.skipped_chunks;
+            }
+            Err(e) => {
+                eprintln!("[generate_text] Reconnect failed: {}", e);
+                break;
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Emit the terminal event for a finished stream
+fn emit_generation_end(window: &Window, outcome: &StreamOutcome, mut payload: GenerationEvent) {
+    payload.skipped_chunks = Some(outcome.skipped_chunks);
+    payload.reconnects = Some(outcome.reconnects);
+    let name = match outcome.finish_reason.as_str() {
+        "timeout" => "generation-timeout",
+        "canceled" => "generation-canceled",
+        _ => "generation-complete",
+    };
+    println!("[generate_text] Emitting {}", name);
+    if let Err(e) = window.emit(name, payload) {
+        println!("[generate_text] Failed to emit {}: {:?}", name, e);
+    }
+}
+
+/// Optional knobs of `generate_text`
+#[derive(Deserialize, Default)]
+struct GenerateTextOptions {
+    /// Generated from the conversation id and time when missing
+    #[serde(default, rename = "requestId")]
+    request_id: Option<String>,
+    #[serde(default, rename = "responseFormat")]
+    response_format: Option<serde_json::Value>,
+    #[serde(default)]
+    grammar: Option<String>,
+}
+
+#[tauri::command]
+async fn generate_text(
+    conversation_id: i64,
+    user_message: String,
+    options: Option<GenerateTextOptions>,
+    window: Window,
+    db: State<'_, DbState>,
+    generations: State<'_, GenerationManager>,
+) -> Result<String, AppError> {
+    let GenerateTextOptions {
+        request_id,
+        response_format,
+        grammar,
+    } = options.unwrap_or_default();
+    let request_id = request_id.unwrap_or_else(|| {
+        format!(
+            "{}-{}",
+            conversation_id,
+            chrono::Utc::now().timestamp_millis()
+        )
+    });
+    let event = |content: &str| GenerationEvent {
+        request_id: request_id.clone(),
+        conversation_id,
+        content: content.to_string(),
+        skipped_chunks: None,
+        reconnects: None,
+    };
+    // Held until we return, whichever way
+    let active = generations.register(&request_id, Some(conversation_id))?;
+
+    // Load conversation
+    let conversation = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::get_conversation(&conn, conversation_id)?
+    };
+
+    // Load message history
+    let messages = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::list_messages(&conn, conversation_id)?
+    };
+
+    let strict_rules = chat_strict_rules(window.app_handle(), &conversation, &user_message)?;
+    let mut chat_messages = history_chat_messages(&conversation, messages, strict_rules.as_deref());
+
+    // Add new user message
+    chat_messages.push(llama::ChatMessage {
+        role: "user".to_string(),
+        content: user_message,
+    });
+
+    let mut payload = chat_payload(&conversation, chat_messages);
+    payload.response_format = response_format;
+    payload.grammar = grammar.filter(|g| !g.trim().is_empty());
+    let outcome = stream_with_reconnect(
+        &payload,
+        idle_timeout_secs(&conversation),
+        &active.cancel,
+        &window,
+        "generation",
+        event,
+    )
+    .await?;
+
+    // Save assistant message to DB (a timeout or cancel with nothing generated leaves no row)
+    let nothing_generated = outcome.text.is_empty()
+        && (outcome.finish_reason == "timeout" || outcome.finish_reason == "canceled");
+    if !nothing_generated {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let message_id = db::add_message(&mut conn, conversation_id, "assistant", &outcome.text)?;
+        db::set_message_finish_reason(&conn, message_id, Some(&outcome.finish_reason))?;
+        db::set_message_generation_params(
+            &conn,
+            message_id,
+            &generation_params(&payload, &outcome),
+        )?;
+        if outcome.reconnects > 0 {
+            db::set_message_reconnected(&conn, message_id)?;
+        }
+    }
+
+    emit_generation_end(&window, &outcome, event(&outcome.text));
+
+    Ok(request_id)
+}
+
+/// Resume the last assistant message when it was cut off (max_tokens, timeout, dropped stream)
+///
+/// Streams with the usual `generation-*` events; the continuation is appended
+/// to the same message row. Returns the request id.
+#[tauri::command]
+async fn continue_generation(
+    conversation_id: i64,
+    request_id: Option<String>,
+    window: Window,
+    db: State<'_, DbState>,
+    generations: State<'_, GenerationManager>,
+) -> Result<String, AppError> {
+    let request_id = request_id.unwrap_or_else(|| {
+        format!(
+            "{}-{}",
+            conversation_id,
+            chrono::Utc::now().timestamp_millis()
+        )
+    });
+    let event = |content: &str| GenerationEvent {
+        request_id: request_id.clone(),
+        conversation_id,
+        content: content.to_string(),
+        skipped_chunks: None,
+        reconnects: None,
+    };
+    let active = generations.register(&request_id, Some(conversation_id))?;
+
+    let (conversation, messages) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            db::get_conversation(&conn, conversation_id)?,
+            db::list_messages(&conn, conversation_id)?,
+        )
+    };
+
+    let last = messages
+        .last()
+        .ok_or_else(|| AppError::InvalidInput("Conversation has no messages".to_string()))?;
+    let resumable = last.role == "assistant"
+        && last
+            .finish_reason
+            .as_deref()
+            .is_some_and(|r| RESUMABLE_FINISH_REASONS.contains(&r));
+    if !resumable {
+        return Err(AppError::InvalidInput(
+            "Last message is not a cut-off assistant response".to_string(),
+        ));
+    }
+    let message_id = last.id;
+
+    let last_user = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.clone())
+        .unwrap_or_default();
+    let strict_rules = chat_strict_rules(window.app_handle(), &conversation, &last_user)?;
+    let mut chat_messages = history_chat_messages(&conversation, messages, strict_rules.as_deref());
+    chat_messages.push(llama::ChatMessage {
+        role: "user".to_string(),
+        content: CONTINUE_PROMPT.to_string(),
+    });
+
+    let payload = chat_payload(&conversation, chat_messages);
+    let outcome = stream_chat_completion(
+        &payload,
+        idle_timeout_secs(&conversation),
+        &active.cancel,
+        &window,
+        "generation",
+        event,
+    )
+    .await?;
+
+    {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::append_message_content(
+            &mut conn,
+            message_id,
+            &outcome.text,
+            Some(&outcome.finish_reason),
+        )?;
+    }
+
+    emit_generation_end(&window, &outcome, event(&outcome.text));
+
+    Ok(request_id)
+}
+
+/// Generate another answer to the same user turn, kept next to the existing ones
+///
+/// The new variant becomes the active one (earlier ones stay reachable through
+/// `list_variants`). Streams with the usual `generation-*` events and returns
+/// the request id.
+#[tauri::command]
+async fn regenerate_alternative(
+    conversation_id: i64,
+    message_id: i64,
+    request_id: Option<String>,
+    window: Window,
+    db: State<'_, DbState>,
+    generations: State<'_, GenerationManager>,
+) -> Result<String, AppError> {
+    let request_id = request_id.unwrap_or_else(|| {
+        format!(
+            "{}-{}",
+            conversation_id,
+            chrono::Utc::now().timestamp_millis()
+        )
+    });
+    let event = |content: &str| GenerationEvent {
+        request_id: request_id.clone(),
+        conversation_id,
+        content: content.to_string(),
+        skipped_chunks: None,
+        reconnects: None,
+    };
+    let active = generations.register(&request_id, Some(conversation_id))?;
+
+    let (conversation, messages, target) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            db::get_conversation(&conn, conversation_id)?,
+            db::list_messages(&conn, conversation_id)?,
+            db::get_message(&conn, message_id)?,
+        )
+    };
+    if target.conversation_id != conversation_id || target.role != "assistant" {
+        return Err(AppError::InvalidInput(
+            "Only assistant messages of this conversation can be regenerated".to_string(),
+        ));
+    }
+
+    // The user turn being answered: recorded once a group exists, else the one before
+    let parent_id = match target.parent_message_id {
+        Some(id) => id,
+        None => {
+            let position = messages
+                .iter()
+                .position(|m| m.id == target.id)
+                .ok_or_else(|| AppError::NotFound("Message is not shown".to_string()))?;
+            messages[..position]
+                .iter()
+                .rev()
+                .find(|m| m.role == "user")
+                .map(|m| m.id)
+                .ok_or_else(|| {
+                    AppError::InvalidInput("No user message precedes this answer".to_string())
+                })?
+        }
+    };
+    let cut = messages
+        .iter()
+        .position(|m| m.id == parent_id)
+        .ok_or_else(|| AppError::NotFound("Answered user message not found".to_string()))?;
+    let history: Vec<db::Message> = messages.into_iter().take(cut + 1).collect();
+
+    let strict_rules =
+        chat_strict_rules(window.app_handle(), &conversation, &history[cut].content)?;
+    let payload = chat_payload(
+        &conversation,
+        history_chat_messages(&conversation, history, strict_rules.as_deref()),
+    );
+    let outcome = stream_with_reconnect(
+        &payload,
+        idle_timeout_secs(&conversation),
+        &active.cancel,
+        &window,
+        "generation",
+        event,
+    )
+    .await?;
+
+    // Same rule as generate_text: nothing generated leaves the existing variants as they are
+    let nothing_generated = outcome.text.is_empty()
+        && (outcome.finish_reason == "timeout" || outcome.finish_reason == "canceled");
+    if !nothing_generated {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        let variant_id = db::add_variant(
+            &mut conn,
+            target.id,
+            parent_id,
+            &outcome.text,
+            Some(&outcome.finish_reason),
+        )?;
+        db::set_message_generation_params(
+            &conn,
+            variant_id,
+            &generation_params(&payload, &outcome),
+        )?;
+        if outcome.reconnects > 0 {
+            db::set_message_reconnected(&conn, variant_id)?;
+        }
+    }
+
+    emit_generation_end(&window, &outcome, event(&outcome.text));
+
+    Ok(request_id)
+}
+
+/// All answers generated for the same user turn as `message_id`
+#[tauri::command]
+async fn list_variants(
+    message_id: i64,
+    db: State<'_, DbState>,
+) -> Result<Vec<db::Message>, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::list_variants(&conn, message_id).map_err(AppError::from)
+}
+
+/// Switch to another variant, used as history from the next turn on
+#[tauri::command]
+async fn set_active_variant(message_id: i64, db: State<'_, DbState>) -> Result<(), AppError> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::set_active_variant(&mut conn, message_id).map_err(AppError::from)
+}
+
+#[derive(Deserialize, Default)]
+struct CompletionParams {
+    temperature: Option<f32>,
+    #[serde(rename = "topP")]
+    top_p: Option<f32>,
+    #[serde(rename = "maxTokens")]
+    max_tokens: Option<i32>,
+    #[serde(rename = "repeatPenalty")]
+    repeat_penalty: Option<f32>,
+    #[serde(rename = "topK")]
+    top_k: Option<i32>,
+    #[serde(rename = "minP")]
+    min_p: Option<f32>,
+    stop: Option<Vec<String>>,
+}
+
+/// Payload of `completion-*` events
+#[derive(Serialize, Clone)]
+struct CompletionEvent {
+    request_id: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct CompletionResult {
+    request_id: String,
+    text: String,
+    finish_reason: String,
+}
+
+/// Raw prompt completion through `/v1/completions`, without the chat template
+///
+/// Starts the preset's server if needed, then streams `completion-chunk`
+/// events and ends with `completion-complete` (or `completion-error`).
+/// Nothing is stored in the database.
+#[tauri::command]
+async fn generate_completion(
+    preset_id: String,
+    prompt: String,
+    params: Option<CompletionParams>,
+    request_id: Option<String>,
+    window: Window,
+    app: AppHandle,
+    generations: State<'_, GenerationManager>,
+) -> Result<CompletionResult, AppError> {
+    if prompt.is_empty() {
+        return Err(AppError::InvalidInput("Prompt is empty".to_string()));
+    }
+    let params = params.unwrap_or_default();
+    validate_sampling(params.top_k, params.min_p)?;
+    let request_id = request_id
+        .unwrap_or_else(|| format!("completion-{}", chrono::Utc::now().timestamp_millis()));
+    let event = |content: &str| CompletionEvent {
+        request_id: request_id.clone(),
+        content: content.to_string(),
     };
+    let active = generations.register(&request_id, None)?;
 
-    eprintln!(
-        "[generate_text] Parameters: temp={}, top_p={}, max_tokens={}, repeat_penalty={}",
-        payload.temperature, payload.top_p, payload.max_tokens, payload.repeat_penalty
-    );
+    start_llama_with_preset(preset_id.clone(), window.clone(), app.clone()).await?;
 
-    // Send request to llama-server
-    let server_url = llama::get_server_url();
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(120))
-        .build()
-        .map_err(|e| e.to_string())?;
+    let payload = llama::CompletionRequest {
+        model: preset_id,
+        prompt,
+        stream: true,
+        temperature: params.temperature.unwrap_or(0.7),
+        top_p: params.top_p.unwrap_or(0.9),
+        max_tokens: params.max_tokens.unwrap_or(512),
+        repeat_penalty: params.repeat_penalty.unwrap_or(1.1),
+        top_k: params.top_k,
+        min_p: params.min_p,
+        stop: params.stop.filter(|s| !s.is_empty()),
+    };
+    let outcome = stream_completion(
+        "/v1/completions",
+        &payload,
+        DEFAULT_GENERATION_TIMEOUT_SECS,
+        &active.cancel,
+        &window,
+        "completion",
+        event,
+    )
+    .await?;
 
-    let response = client
-        .post(format!("{}/v1/chat/completions", server_url))
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| {
-            if e.to_string().contains("Connection refused") {
-                "llama-server is not running. Please start it first.".to_string()
-            } else {
-                format!("Failed to connect to llama-server: {}", e)
-            }
-        })?;
+    window
+        .emit("completion-complete", event(&outcome.text))
+        .ok();
+    Ok(CompletionResult {
+        request_id,
+        text: outcome.text,
+        finish_reason: outcome.finish_reason,
+    })
+}
 
-    if !response.status().is_success() {
-        let error_msg = format!("llama-server returned error: {}", response.status());
-        window.emit("generation-error", &error_msg).ok();
-        return Err(error_msg);
+/// Stop a streaming generation; the partial answer is kept with finish reason `canceled`
+#[tauri::command]
+fn cancel_generation(
+    request_id: String,
+    generations: State<'_, GenerationManager>,
+) -> Result<(), AppError> {
+    let active = generations.active.lock().map_err(|e| e.to_string())?;
+    match active.get(&request_id) {
+        Some(slot) => {
+            slot.cancel.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(AppError::NotFound(format!(
+            "No generation running for request {}",
+            request_id
+        ))),
     }
+}
 
-    // Stream response
-    let mut stream = response.bytes_stream();
-    let mut buffer = String::new();
-    let mut accumulated = String::new();
-    let mut finished = false;
-
-    println!("[generate_text] Starting to stream response...");
-
-    while let Some(chunk) = stream.next().await {
-        let bytes = chunk.map_err(|e| e.to_string())?;
-        let text = String::from_utf8_lossy(&bytes);
+#[derive(Serialize, Clone)]
+struct EmergencyStopReport {
+    downloads_canceled: usize,
+    generations_canceled: usize,
+    /// Error from stopping llama-server, if any
+    server_error: Option<String>,
+}
 
-        buffer.push_str(&text);
+/// Kill switch: cancel every download and generation, then stop llama-server
+///
+/// Safe to call when nothing is running. Emits `emergency-stopped` with the report.
+#[tauri::command]
+fn emergency_stop(
+    window: Window,
+    dm: State<'_, DownloadManager>,
+    generations: State<'_, GenerationManager>,
+) -> Result<EmergencyStopReport, AppError> {
+    let downloads_canceled = {
+        let map = dm.inner.lock().map_err(|e| e.to_string())?;
+        map.values()
+            // Paused downloads have no task to stop, their .part is kept
+            .filter(|entry| entry.state.status == "running")
+            .inspect(|entry| entry.cancel.store(true, Ordering::SeqCst))
+            .count()
+    };
+    let generations_canceled = generations.cancel_all();
+    let server_error = llama_install::stop_server_process(window.clone()).err();
 
-        // Process complete lines
-        while let Some(pos) = buffer.find('\n') {
-            let line = buffer[..pos].trim().to_string();
-            buffer = buffer[pos + 1..].to_string();
+    let report = EmergencyStopReport {
+        downloads_canceled,
+        generations_canceled,
+        server_error,
+    };
+    window.emit("emergency-stopped", report.clone()).ok();
+    Ok(report)
+}
 
-            if line.is_empty() {
-                continue;
-            }
+// ============= LLAMA-SERVER INSTALLATION & MANAGEMENT =============
 
-            println!("[generate_text] Raw SSE line: {}", line);
+#[tauri::command]
+async fn check_llama_server(
+    app: tauri::AppHandle,
+) -> Result<llama_install::ServerStatus, AppError> {
+    llama_install::check_server_binary(&app).map_err(AppError::from)
+}
 
-            if let Some(json_str) = line.strip_prefix("data: ") {
-                if json_str == "[DONE]" {
-                    println!("[generate_text] Received [DONE], finishing stream");
-                    finished = true;
-                    break;
-                }
+#[derive(Serialize)]
+struct RunningServerInfo {
+    /// Parameters of the process we started, `None` when it isn't running
+    launch: Option<llama_install::ServerLaunch>,
+    /// Whether /v1/models answered
+    reachable: bool,
+    /// Model ids reported by /v1/models
+    served_models: Vec<String>,
+    /// Whether the served model is the one we launched, `None` when it can't be told
+    model_matches: Option<bool>,
+}
 
-                // Parse SSE chunk
-                match serde_json::from_str::<llama::SSEChunk>(json_str) {
-                    Ok(sse_chunk) => {
-                        if let Some(choice) = sse_chunk.choices.first() {
-                            // Extract content delta
-                            if let Some(content) = &choice.delta.content {
-                                if !content.is_empty() {
-                                    accumulated.push_str(content);
-                                    println!("[generate_text] Emitting chunk: {}", content);
-                                    // Emit chunk to frontend
-                                    if let Err(e) = window.emit("generation-chunk", content) {
-                                        println!("[generate_text] Failed to emit chunk: {:?}", e);
-                                    }
-                                }
-                            }
+/// llama-server reports the `-m` path (or an alias) as the model id, compare file names
+fn same_model(served_id: &str, model_path: &str) -> bool {
+    served_id == model_path || Path::new(served_id).file_name() == Path::new(model_path).file_name()
+}
 
-                            // Check if generation is complete
-                            if let Some(reason) = &choice.finish_reason {
-                                if reason == "stop" || reason == "length" {
-                                    println!("[generate_text] Finish reason: {}", reason);
-                                    finished = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("[generate_text] ⚠️ PARSE ERROR: {} | JSON: {}", e, json_str);
-                        eprintln!("[generate_text] ⚠️ This chunk was SKIPPED. Check if llama-server is sending malformed JSON.");
-                        // Continue processing next chunks instead of silently failing
-                    }
-                }
-            }
-        }
+/// Model ids listed by llama-server's /v1/models, `None` when it can't be reached
+async fn fetch_served_models() -> Option<Vec<String>> {
+    let client = http::client_builder()
+        .ok()?
+        .timeout(std::time::Duration::from_secs(3))
+        .build()
+        .ok()?;
+    let resp = client
+        .get(format!("{}/v1/models", llama::get_server_url()))
+        .send()
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let body: serde_json::Value = resp.json().await.ok()?;
+    Some(
+        body["data"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m["id"].as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    )
+}
 
-        // If the stream indicated completion, exit the outer loop promptly
-        if finished {
-            break;
+/// Which model the running server has loaded and how it was started
+///
+/// Lets the UI warn when the running model doesn't match a conversation's preset.
+#[tauri::command]
+async fn get_running_server_info() -> Result<RunningServerInfo, AppError> {
+    let launch = llama_install::running_server_launch();
+    let served = fetch_served_models().await;
+    let model_matches = match (&launch, &served) {
+        (Some(l), Some(models)) if !models.is_empty() => {
+            Some(models.iter().any(|id| same_model(id, &l.model_path)))
         }
-    }
+        _ => None,
+    };
+    Ok(RunningServerInfo {
+        launch,
+        reachable: served.is_some(),
+        served_models: served.unwrap_or_default(),
+        model_matches,
+    })
+}
 
-    println!(
-        "[generate_text] Streaming complete. Total accumulated: {} chars",
-        accumulated.len()
-    );
+#[derive(Serialize)]
+struct TokenCount {
+    count: usize,
+    /// True when the server couldn't be asked and `count` is a chars/4 estimate
+    approximate: bool,
+    /// Token ids, only when requested and counted by the server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ids: Option<Vec<i64>>,
+}
 
-    // Save assistant message to DB
-    {
-        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
-        db::add_message(&mut conn, conversation_id, "assistant", &accumulated)
-            .map_err(|e| e.to_string())?;
+/// Token ids from llama-server's /tokenize, `None` when it can't be reached
+async fn server_tokenize(text: &str) -> Option<Vec<i64>> {
+    let client = http::client_builder()
+        .ok()?
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .ok()?;
+    let resp = client
+        .post(format!("{}/tokenize", llama::get_server_url()))
+        .json(&serde_json::json!({ "content": text }))
+        .send()
+        .await
+        .ok()?;
+    if !resp.status().is_success() {
+        return None;
     }
+    let body: serde_json::Value = resp.json().await.ok()?;
+    body["tokens"]
+        .as_array()
+        .map(|tokens| tokens.iter().filter_map(|t| t.as_i64()).collect())
+}
 
-    // Emit completion event
-    println!("[generate_text] Emitting generation-complete");
-    if let Err(e) = window.emit("generation-complete", &accumulated) {
-        println!("[generate_text] Failed to emit complete: {:?}", e);
+/// Count tokens with the running server's tokenizer
+///
+/// With a `preset_id` its server is started first if needed; without one the
+/// running server is used as-is, so a live token meter never spawns a model.
+/// Falls back to a chars/4 estimate, flagged `approximate`, when the
+/// tokenizer endpoint is unavailable.
+#[tauri::command]
+async fn count_tokens(
+    text: String,
+    preset_id: Option<String>,
+    include_ids: Option<bool>,
+    window: Window,
+    app: AppHandle,
+) -> Result<TokenCount, AppError> {
+    if let Some(preset_id) = preset_id {
+        if let Err(e) = start_llama_with_preset(preset_id, window, app).await {
+            eprintln!("[count_tokens] Server not started, estimating: {}", e);
+        }
+    }
+    match server_tokenize(&text).await {
+        Some(ids) => Ok(TokenCount {
+            count: ids.len(),
+            approximate: false,
+            ids: include_ids.unwrap_or(false).then_some(ids),
+        }),
+        None => Ok(TokenCount {
+            count: text.chars().count().div_ceil(4),
+            approximate: true,
+            ids: None,
+        }),
     }
+}
 
-    Ok(())
+#[derive(Serialize)]
+struct SlotInfo {
+    id: i64,
+    n_ctx: Option<u64>,
+    /// Busy generating for a request
+    is_processing: bool,
 }
 
-// ============= LLAMA-SERVER INSTALLATION & MANAGEMENT =============
+/// What the running llama-server reports about itself, `None` for fields its version lacks
+#[derive(Serialize)]
+struct ServerPropsInfo {
+    /// Context window actually allocated (per slot on recent builds)
+    n_ctx: Option<u64>,
+    total_slots: Option<u64>,
+    model_path: Option<String>,
+    /// Only reported by some builds, unknown otherwise
+    embeddings: Option<bool>,
+    vision: Option<bool>,
+    chat_template: Option<String>,
+    build_info: Option<String>,
+    /// From `/slots`, `None` when that endpoint is disabled
+    slots: Option<Vec<SlotInfo>>,
+}
 
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum ServerProps {
+    Available(ServerPropsInfo),
+    /// The server answers but has no `/props` (older llama.cpp)
+    NotAvailable,
+}
+
+async fn server_slots(client: &reqwest::Client, base: &str) -> Option<Vec<SlotInfo>> {
+    let resp = client.get(format!("{}/slots", base)).send().await.ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let body: serde_json::Value = resp.json().await.ok()?;
+    Some(
+        body.as_array()?
+            .iter()
+            .map(|slot| SlotInfo {
+                id: slot["id"].as_i64().unwrap_or_default(),
+                n_ctx: slot["n_ctx"].as_u64(),
+                // Older builds report a numeric `state` (0 = idle) instead
+                is_processing: slot["is_processing"]
+                    .as_bool()
+                    .or_else(|| slot["state"].as_u64().map(|s| s != 0))
+                    .unwrap_or(false),
+            })
+            .collect(),
+    )
+}
+
+/// Query llama-server's `/props` (and `/slots`) for its real configuration
 #[tauri::command]
-async fn check_llama_server(app: tauri::AppHandle) -> Result<llama_install::ServerStatus, String> {
-    llama_install::check_server_binary(&app)
+async fn get_server_props() -> Result<ServerProps, AppError> {
+    let client = http::client_builder()?
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let base = llama::get_server_url();
+    let resp = client
+        .get(format!("{}/props", base))
+        .send()
+        .await
+        .map_err(|e| AppError::ServerNotRunning(format!("llama-server unreachable: {}", e)))?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(ServerProps::NotAvailable);
+    }
+    let resp = resp
+        .error_for_status()
+        .map_err(|e| AppError::ServerError(e.to_string()))?;
+    let body: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| AppError::ServerError(format!("Invalid /props response: {}", e)))?;
+
+    let settings = &body["default_generation_settings"];
+    let text = |v: &serde_json::Value| v.as_str().map(str::to_string);
+    Ok(ServerProps::Available(ServerPropsInfo {
+        n_ctx: settings["n_ctx"]
+            .as_u64()
+            .or_else(|| body["n_ctx"].as_u64()),
+        total_slots: body["total_slots"].as_u64(),
+        model_path: text(&body["model_path"]).or_else(|| text(&settings["model"])),
+        embeddings: body["embedding"]
+            .as_bool()
+            .or_else(|| body["embeddings"].as_bool()),
+        vision: body["modalities"]["vision"].as_bool(),
+        chat_template: text(&body["chat_template"]),
+        build_info: text(&body["build_info"]),
+        slots: server_slots(&client, &base).await,
+    }))
 }
 
 #[tauri::command]
-async fn health_check_llama_server() -> Result<bool, String> {
-    let client = reqwest::Client::builder()
+async fn health_check_llama_server() -> Result<bool, AppError> {
+    let client = http::client_builder()?
         .timeout(std::time::Duration::from_secs(3))
         .build()
         .map_err(|e| e.to_string())?;
@@ -952,32 +3591,27 @@ async fn start_llama_for_conversation(
     db: tauri::State<'_, DbState>,
     window: Window,
     app: tauri::AppHandle,
-) -> Result<u32, String> {
-    // Get conversation preset_id from database
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let conversation = db::get_conversation(&conn, conversation_id).map_err(|e| e.to_string())?;
-
-    // Load pack info
-    const PACKS_JSON: &str = include_str!("../pack-sources.json");
-    let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
-    let pack = packs
-        .into_iter()
-        .find(|p| p.id == conversation.preset_id)
-        .ok_or_else(|| "Unknown preset for this conversation".to_string())?;
-
-    // Build model path
-    let model_path = models_root_dir(&app)?.join(&pack.id).join(&pack.filename);
+) -> Result<u32, AppError> {
+    // Get conversation preset_id from database (released before reading settings below)
+    let conversation = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::get_conversation(&conn, conversation_id)?
+    };
 
-    if !model_path.exists() {
-        return Err(format!(
-            "Model '{}' is not downloaded. Please download it from the onboarding page first.",
-            pack.id
-        ));
-    }
+    // Packs and models registered by scan_models alike
+    let model_path = match preset_model_path(&app, &conversation.preset_id) {
+        Err(AppError::ModelNotFound(_)) => {
+            return Err(AppError::ModelNotFound(format!(
+                "Model '{}' is not downloaded. Please download it from the onboarding page first.",
+                conversation.preset_id
+            )));
+        }
+        other => other?,
+    };
 
-    // Start server with this model
-    let model_path_str = format!("models/{}/{}", pack.id, pack.filename);
-    llama_install::start_server_process(model_path_str, 2048, window, &app)
+    // Start server with this model (absolute, the models dir may live outside the base dir)
+    let model_path_str = model_path.to_string_lossy().to_string();
+    start_server(model_path_str, default_ctx_size(&app), window, &app)
 }
 
 // ===== AI prompt generation (non-streaming) =====
@@ -988,10 +3622,23 @@ struct GeneratePromptAiArgs {
     intent: String,
     #[serde(default)]
     clarifications: Vec<QAItem>,
-    #[serde(rename = "strictMode")]
-    strict_mode: bool,
+    /// Defaults to the `strict_mode` setting
+    #[serde(default, rename = "strictMode")]
+    strict_mode: Option<bool>,
+    /// Forces the prompt's language, detected from the intent otherwise
     #[serde(default)]
     locale: Option<String>,
+    /// Emit `prompt-gen-chunk`/`prompt-gen-complete` while generating
+    #[serde(default)]
+    stream: bool,
+    #[serde(default, rename = "responseFormat")]
+    response_format: Option<serde_json::Value>,
+    #[serde(default)]
+    grammar: Option<String>,
+    #[serde(default, rename = "presencePenalty")]
+    presence_penalty: Option<f32>,
+    #[serde(default, rename = "frequencyPenalty")]
+    frequency_penalty: Option<f32>,
 }
 
 #[derive(Deserialize)]
@@ -1024,18 +3671,120 @@ struct GenerateDialogueArgs {
     preset_id: String,
     #[serde(default)]
     history: Vec<DialogueMsg>,
+    /// Defaults to the `strict_mode` setting
     #[serde(default)]
-    strict_mode: bool,
+    strict_mode: Option<bool>,
+    /// Forces the prompt's language, detected from the user's turns otherwise
     #[serde(default)]
     locale: Option<String>,
+    #[serde(default, rename = "presencePenalty")]
+    presence_penalty: Option<f32>,
+    #[serde(default, rename = "frequencyPenalty")]
+    frequency_penalty: Option<f32>,
+    /// Prompt session whose history is replaced by this round's
+    #[serde(default, rename = "sessionId")]
+    session_id: Option<i64>,
 }
-#[derive(Serialize)]
+#[derive(Debug, PartialEq, Serialize)]
 #[serde(tag = "status")]
 enum DialogueResult {
     #[serde(rename = "questions")]
     Questions { questions: Vec<String> },
     #[serde(rename = "final")]
     Final { prompt: String },
+    /// Aborted through `cancel_prompt_dialogue`
+    #[serde(rename = "canceled")]
+    Canceled,
+}
+
+/// Markers of the prompt-dialogue protocol, matched case-insensitively
+const FINAL_MARKERS: [&str; 2] = ["PROMPT_FINAL", "PROMPT FINAL"];
+const QUESTIONS_MARKERS: [&str; 1] = ["QUESTIONS"];
+
+/// Byte offsets just past every `<marker>:` in `text`, in order
+///
+/// Tolerates markdown around the marker (`**PROMPT_FINAL:**`, `## Questions :`).
+fn marker_ends(text: &str, markers: &[&str]) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let mut ends = Vec::new();
+    for marker in markers {
+        let m = marker.as_bytes();
+        for start in 0..bytes.len().saturating_sub(m.len() - 1) {
+            if !bytes[start..start + m.len()].eq_ignore_ascii_case(m) {
+                continue;
+            }
+            let mut end = start + m.len();
+            while end < bytes.len() && matches!(bytes[end], b'*' | b'_' | b' ') {
+                end += 1;
+            }
+            if end < bytes.len() && bytes[end] == b':' {
+                end += 1;
+                while end < bytes.len() && matches!(bytes[end], b'*' | b'_') {
+                    end += 1;
+                }
+                ends.push(end);
+            }
+        }
+    }
+    ends.sort_unstable();
+    ends
+}
+
+/// Drop markdown code fence lines (```lang / ```)
+fn strip_code_fences(text: &str) -> String {
+    text.lines()
+        .filter(|l| !l.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Strip list bullets and numbering from a question line
+fn clean_question_line(line: &str) -> &str {
+    let line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with('.') || rest.starts_with(')')) {
+        rest[1..].trim()
+    } else {
+        line.trim()
+    }
+}
+
+/// Classify a prompt-dialogue reply
+///
+/// Models often wrap the markers in markdown or code fences, add a preamble,
+/// change their case, or echo both. A final prompt wins over questions; with
+/// no marker the whole reply is treated as a single clarifying question.
+fn parse_dialogue_reply(content: &str) -> DialogueResult {
+    let text = strip_code_fences(content);
+
+    // First final marker: the prompt itself may mention "questions:" or similar
+    if let Some(&end) = marker_ends(&text, &FINAL_MARKERS).first() {
+        let prompt = text[end..].trim();
+        if !prompt.is_empty() {
+            return DialogueResult::Final {
+                prompt: prompt.to_string(),
+            };
+        }
+    }
+
+    // Last questions marker: skips preambles like "here are my questions:"
+    if let Some(&end) = marker_ends(&text, &QUESTIONS_MARKERS).last() {
+        let questions: Vec<String> = text[end..]
+            .lines()
+            .map(clean_question_line)
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string())
+            .collect();
+        if !questions.is_empty() {
+            return DialogueResult::Questions { questions };
+        }
+    }
+
+    // Fallback: treat as assistant question in a single block
+    DialogueResult::Questions {
+        questions: vec![text.trim().to_string()],
+    }
 }
 
 #[tauri::command]
@@ -1043,26 +3792,46 @@ async fn generate_prompt_ai_dialogue(
     args: GenerateDialogueArgs,
     window: Window,
     app: AppHandle,
-) -> Result<DialogueResult, String> {
-    // Ensure server is started
+    dialogue: State<'_, PromptDialogueState>,
+) -> Result<DialogueResult, AppError> {
+    // Fail before the round rather than after it for an unknown session
+    if let Some(session_id) = args.session_id {
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::get_prompt_session(&conn, session_id)?;
+    }
+
+    // Registered up front so a cancel during server start is honored right after it
+    let (_round, abort_registration) = dialogue.register()?;
+
+    // Ensure server is started (never aborted midway, it would leave a half-started process)
     let _ = start_llama_with_preset(args.preset_id.clone(), window.clone(), app.clone()).await;
 
-    let language = match args.locale.as_deref() {
-        Some("en") | Some("en-US") => "English",
-        Some(l) if l.starts_with("fr") => "français",
-        None => "français",
-        _ => "français",
-    };
+    // What the user typed decides the prompt's language, unless a locale is forced
+    let user_text: Vec<&str> = args
+        .history
+        .iter()
+        .filter(|m| m.role == "user")
+        .map(|m| m.content.as_str())
+        .collect();
+    let detected = lang::resolve(args.locale.as_deref(), &user_text.join("\n"));
+    let language = detected.eng_name();
 
-    let mut strict = String::new();
-    if args.strict_mode {
-        strict.push_str("RÈGLES STRICTES - ZÉRO INVENTION\n1) Suivre uniquement les instructions explicites\n2) Aucune extrapolation\n3) Si une info manque, poser jusqu'à 3 questions concises\n4) Respecter langue/format demandés\n\n");
-    }
+    let template = active_prompt_template(&app)?;
+    let strict = if args
+        .strict_mode
+        .unwrap_or_else(|| default_strict_mode(&app))
+    {
+        custom_strict_rules(&app, lang::code(detected))
+            .unwrap_or_else(|| template.dialogue_strict_rules.clone())
+    } else {
+        String::new()
+    };
 
     // Protocol for iterative prompting
-    let system_proto = format!(
-        "{}Tu es un ingénieur de prompt. Conduis un court dialogue pour clarifier le besoin.\nProtocole de réponse unique à chaque tour:\n- Si des informations sont manquantes: réponds UNIQUEMENT sous la forme:\nQUESTIONS:\n- <Q1>\n- <Q2>\n- <Q3 (optionnelle)>\n- Sinon, si tout est clair: réponds UNIQUEMENT sous la forme:\nPROMPT_FINAL:\n<Prompt système complet et prêt à l'emploi en {}>\nAucun texte avant/après, pas d'explication.",
-        strict, language
+    let system_proto = prompt_templates::render(
+        &template.dialogue_system,
+        &[("strict", strict.as_str()), ("language", language)],
     );
 
     // Build messages
@@ -1080,7 +3849,7 @@ async fn generate_prompt_ai_dialogue(
     if messages.len() == 1 {
         messages.push(crate::llama::ChatMessage {
             role: "user".into(),
-            content: "Bonjour".into(),
+            content: template.dialogue_opening.clone(),
         });
     }
 
@@ -1092,51 +3861,242 @@ async fn generate_prompt_ai_dialogue(
         top_p: 0.9,
         max_tokens: 512,
         repeat_penalty: 1.1,
+        presence_penalty: args.presence_penalty,
+        frequency_penalty: args.frequency_penalty,
+        top_k: None,
+        min_p: None,
+        response_format: None,
+        grammar: None,
+        seed: None,
     };
 
     let server_url = crate::llama::get_server_url();
-    let client = reqwest::Client::builder()
+    let client = http::client_builder()?
         .timeout(std::time::Duration::from_secs(60))
         .build()
         .map_err(|e| e.to_string())?;
-    let resp = client
-        .post(format!("{}/v1/chat/completions", server_url))
-        .json(&payload)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to connect to llama-server: {}", e))?;
-    if !resp.status().is_success() {
-        return Err(format!("llama-server returned error: {}", resp.status()));
-    }
-    let txt = resp.text().await.map_err(|e| e.to_string())?;
-    let parsed: ChatResp =
-        serde_json::from_str(&txt).map_err(|e| format!("Invalid response: {} | {}", e, txt))?;
+    let request = async {
+        let resp = client
+            .post(format!("{}/v1/chat/completions", server_url))
+            .json(&payload)
+            .send()
+            .await
+            .map_err(llama_request_error)?;
+        if !resp.status().is_success() {
+            return Err(AppError::ServerError(format!(
+                "llama-server returned error: {}",
+                resp.status()
+            )));
+        }
+        resp.text()
+            .await
+            .map_err(|e| AppError::Other(e.to_string()))
+    };
+    let result = Abortable::new(request, abort_registration).await;
+    let txt = match result {
+        Ok(txt) => txt?,
+        Err(_aborted) => return Ok(DialogueResult::Canceled),
+    };
+    let parsed: ChatResp = serde_json::from_str(&txt)
+        .map_err(|e| AppError::ServerError(format!("Invalid response: {} | {}", e, txt)))?;
     let content = parsed
         .choices
         .first()
         .map(|c| c.message.content.clone())
         .unwrap_or_default();
 
-    // Parse protocol
-    let trimmed = content.trim();
-    if let Some(rest) = trimmed.strip_prefix("PROMPT_FINAL:") {
-        let prompt = rest.trim().to_string();
-        return Ok(DialogueResult::Final { prompt });
-    }
-    if let Some(rest) = trimmed.strip_prefix("QUESTIONS:") {
-        let qs: Vec<String> = rest
-            .lines()
-            .map(|l| l.trim())
-            .filter(|l| !l.is_empty())
-            .map(|l| l.trim_start_matches('-').trim().to_string())
-            .filter(|l| !l.is_empty())
+    let result = parse_dialogue_reply(&content);
+    if let Some(session_id) = args.session_id {
+        let mut history: Vec<db::PromptTurn> = args
+            .history
+            .iter()
+            .map(|m| db::PromptTurn {
+                role: m.role.clone(),
+                content: m.content.clone(),
+            })
             .collect();
-        return Ok(DialogueResult::Questions { questions: qs });
+        history.push(db::PromptTurn {
+            role: "assistant".into(),
+            content,
+        });
+        let final_prompt = match &result {
+            DialogueResult::Final { prompt } => Some(prompt.as_str()),
+            _ => None,
+        };
+        let db = app.state::<DbState>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::record_prompt_session_round(&conn, session_id, &history, final_prompt)?;
     }
-    // Fallback: treat as assistant question in a single block
-    Ok(DialogueResult::Questions {
-        questions: vec![trimmed.to_string()],
-    })
+    Ok(result)
+}
+
+/// Selected prompt-generation template, the shipped one when unset or gone
+fn active_prompt_template(app: &AppHandle) -> Result<prompt_templates::PromptTemplate, AppError> {
+    let selected = app.try_state::<DbState>().and_then(|db| {
+        let conn = db.0.lock().ok()?;
+        settings::get_string(&conn, settings::PROMPT_TEMPLATE)
+    });
+    prompt_templates::resolve(selected.as_deref())
+}
+
+/// `strict_mode` setting, off when the database isn't available
+fn default_strict_mode(app: &AppHandle) -> bool {
+    app.try_state::<DbState>()
+        .and_then(|db| {
+            let conn = db.0.lock().ok()?;
+            Some(settings::get_bool(&conn, settings::STRICT_MODE))
+        })
+        .unwrap_or(false)
+}
+
+/// User's strict rules for a language code, `None` when not customized
+fn custom_strict_rules(app: &AppHandle, code: &str) -> Option<String> {
+    let rules = app.try_state::<DbState>().and_then(|db| {
+        let conn = db.0.lock().ok()?;
+        settings::get(&conn, settings::STRICT_RULES).ok().flatten()
+    })?;
+    rules
+        .get(code)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Rules added to a strict conversation's system prompt, in the language of `sample`
+///
+/// Must not be called while holding the database lock.
+fn chat_strict_rules(
+    app: &AppHandle,
+    conversation: &db::Conversation,
+    sample: &str,
+) -> Result<Option<String>, AppError> {
+    if !conversation.strict {
+        return Ok(None);
+    }
+    let code = lang::code(lang::resolve(None, sample));
+    match custom_strict_rules(app, code) {
+        Some(rules) => Ok(Some(rules)),
+        None => Ok(Some(active_prompt_template(app)?.strict_rules)),
+    }
+}
+
+#[derive(Serialize)]
+struct PromptTemplateInfo {
+    #[serde(flatten)]
+    template: prompt_templates::PromptTemplate,
+    builtin: bool,
+    selected: bool,
+}
+
+/// Available prompt-generation templates (shipped + user folder)
+#[tauri::command]
+async fn list_prompt_templates(app: AppHandle) -> Result<Vec<PromptTemplateInfo>, AppError> {
+    let selected = active_prompt_template(&app)?.id;
+    Ok(prompt_templates::list()?
+        .into_iter()
+        .map(|(template, builtin)| PromptTemplateInfo {
+            selected: template.id == selected,
+            template,
+            builtin,
+        })
+        .collect())
+}
+
+/// Use template `id` for prompt generation (`None` restores the default)
+#[tauri::command]
+async fn set_prompt_template(id: Option<String>, db: State<'_, DbState>) -> Result<(), AppError> {
+    let value = match id.filter(|id| id != prompt_templates::DEFAULT_ID) {
+        Some(id) => {
+            if prompt_templates::find(&id)?.is_none() {
+                return Err(AppError::NotFound(format!(
+                    "Unknown prompt template: {}",
+                    id
+                )));
+            }
+            serde_json::Value::String(id)
+        }
+        None => serde_json::Value::Null,
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::set(&conn, settings::PROMPT_TEMPLATE, &value).map_err(AppError::from)
+}
+
+/// Folder where `*.json` prompt templates are picked up
+#[tauri::command]
+fn get_prompt_templates_dir() -> Result<String, AppError> {
+    Ok(prompt_templates::user_dir()?.to_string_lossy().to_string())
+}
+
+/// Abort the prompt-dialogue rounds in flight, which then resolve with status `canceled`
+///
+/// Does nothing when no round is running.
+#[tauri::command]
+fn cancel_prompt_dialogue(dialogue: State<'_, PromptDialogueState>) -> Result<(), AppError> {
+    dialogue.cancel_all()?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct SavePromptSessionArgs {
+    /// Existing session to overwrite, a new one is created when absent
+    #[serde(default)]
+    id: Option<i64>,
+    #[serde(default, rename = "presetId")]
+    preset_id: Option<String>,
+    intent: String,
+    #[serde(default)]
+    history: Vec<db::PromptTurn>,
+    #[serde(default, rename = "finalPrompt")]
+    final_prompt: Option<String>,
+}
+
+/// Store how a system prompt was derived, returns the saved session
+#[tauri::command]
+async fn save_prompt_session(
+    args: SavePromptSessionArgs,
+    db: State<'_, DbState>,
+) -> Result<db::PromptSession, AppError> {
+    if args.intent.trim().is_empty() {
+        return Err(AppError::InvalidInput("Intent cannot be empty".to_string()));
+    }
+    if let Some(turn) = args
+        .history
+        .iter()
+        .find(|t| t.role != "user" && t.role != "assistant")
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported message role: {}",
+            turn.role
+        )));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let id = db::save_prompt_session(
+        &conn,
+        args.id,
+        args.preset_id.as_deref(),
+        args.intent.trim(),
+        &args.history,
+        args.final_prompt
+            .as_deref()
+            .filter(|p| !p.trim().is_empty()),
+    )?;
+    db::get_prompt_session(&conn, id).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn list_prompt_sessions(db: State<'_, DbState>) -> Result<Vec<db::PromptSession>, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::list_prompt_sessions(&conn).map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn get_prompt_session(
+    id: i64,
+    db: State<'_, DbState>,
+) -> Result<db::PromptSession, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    db::get_prompt_session(&conn, id).map_err(AppError::from)
 }
 
 #[tauri::command]
@@ -1144,26 +4104,34 @@ async fn generate_prompt_ai(
     args: GeneratePromptAiArgs,
     window: Window,
     app: AppHandle,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     // Best effort: try to start server with this preset (ignore if already running)
     let _ = start_llama_with_preset(args.preset_id.clone(), window.clone(), app.clone()).await;
 
-    let language = match args.locale.as_deref() {
-        Some("en") | Some("en-US") => "English",
-        Some(l) if l.starts_with("fr") => "français",
-        None => "français",
-        _ => "français",
-    };
-
-    let mut strict = String::new();
-    if args.strict_mode {
-        strict.push_str("RÈGLES STRICTES - ZÉRO INVENTION\n1) Suivre uniquement les instructions explicites\n2) Aucune extrapolation\n3) Si une information critique manque, proposer 2-3 questions courtes\n4) Respect strict de la langue/format\n\n");
+    // What the user typed decides the prompt's language, unless a locale is forced
+    let mut user_text = args.intent.clone();
+    for qa in &args.clarifications {
+        user_text.push('\n');
+        user_text.push_str(&qa.answer);
     }
+    let detected = lang::resolve(args.locale.as_deref(), &user_text);
+    let language = detected.eng_name();
+
+    let template = active_prompt_template(&app)?;
+    let strict = if args
+        .strict_mode
+        .unwrap_or_else(|| default_strict_mode(&app))
+    {
+        custom_strict_rules(&app, lang::code(detected))
+            .unwrap_or_else(|| template.strict_rules.clone())
+    } else {
+        String::new()
+    };
 
     let clarif = if args.clarifications.is_empty() {
         String::new()
     } else {
-        let mut s = String::from("Informations complémentaires:\n");
+        let mut s = template.clarifications_header.clone();
         for qa in &args.clarifications {
             if !qa.answer.trim().is_empty() {
                 s.push_str(&format!("- {} {}\n", qa.question, qa.answer));
@@ -1172,15 +4140,17 @@ async fn generate_prompt_ai(
         s
     };
 
-    let meta_system = format!(
-        "{}Tu es une IA experte en ingénierie de prompt.\n\nMission: Générer le MEILLEUR prompt système pour un assistant de chat afin d'atteindre l'objectif utilisateur.\nContraintes: sortie = UNIQUEMENT le prompt système final, clair, structuré, avec règles précises et langue.\nLangue demandée: {}",
-        strict, language
+    let meta_system = prompt_templates::render(
+        &template.generate_system,
+        &[("strict", strict.as_str()), ("language", language)],
     );
 
-    let user_payload = format!(
-        "Objectif utilisateur: {}\n{}\nGénère le prompt système final maintenant.",
-        args.intent.trim(),
-        clarif
+    let user_payload = prompt_templates::render(
+        &template.generate_user,
+        &[
+            ("objective", args.intent.trim()),
+            ("clarifications", clarif.as_str()),
+        ],
     );
 
     let payload = crate::llama::ChatCompletionRequest {
@@ -1195,15 +4165,51 @@ async fn generate_prompt_ai(
                 content: user_payload,
             },
         ],
-        stream: false,
+        stream: args.stream,
         temperature: 0.2,
         top_p: 0.9,
         max_tokens: 512,
         repeat_penalty: 1.1,
+        presence_penalty: args.presence_penalty,
+        frequency_penalty: args.frequency_penalty,
+        top_k: None,
+        min_p: None,
+        response_format: args.response_format,
+        grammar: args.grammar.filter(|g| !g.trim().is_empty()),
+        seed: None,
     };
 
+    if args.stream {
+        let generations = app.state::<GenerationManager>();
+        let active = generations.register("prompt-gen", None)?;
+        let outcome = stream_chat_completion(
+            &payload,
+            60,
+            &active.cancel,
+            &window,
+            "prompt-gen",
+            |c: &str| c.to_string(),
+        )
+        .await?;
+        match outcome.finish_reason.as_str() {
+            "timeout" => {
+                window.emit("prompt-gen-error", "timeout").ok();
+                return Err(AppError::ServerError(
+                    "Prompt generation timed out".to_string(),
+                ));
+            }
+            "canceled" => {
+                window.emit("prompt-gen-error", "canceled").ok();
+                return Err(AppError::Other("Prompt generation canceled".to_string()));
+            }
+            _ => {}
+        }
+        window.emit("prompt-gen-complete", &outcome.text).ok();
+        return Ok(outcome.text);
+    }
+
     let server_url = crate::llama::get_server_url();
-    let client = reqwest::Client::builder()
+    let client = http::client_builder()?
         .timeout(std::time::Duration::from_secs(60))
         .build()
         .map_err(|e| e.to_string())?;
@@ -1213,22 +4219,26 @@ async fn generate_prompt_ai(
         .json(&payload)
         .send()
         .await
-        .map_err(|e| format!("Failed to connect to llama-server: {}", e))?;
+        .map_err(llama_request_error)?;
     if !resp.status().is_success() {
-        return Err(format!("llama-server returned error: {}", resp.status()));
+        return Err(AppError::ServerError(format!(
+            "llama-server returned error: {}",
+            resp.status()
+        )));
     }
     let txt = resp.text().await.map_err(|e| e.to_string())?;
-    let parsed: ChatResp =
-        serde_json::from_str(&txt).map_err(|e| format!("Invalid response: {} | {}", e, txt))?;
+    let parsed: ChatResp = serde_json::from_str(&txt)
+        .map_err(|e| AppError::ServerError(format!("Invalid response: {} | {}", e, txt)))?;
     if let Some(first) = parsed.choices.first() {
+        validate_structured_output(&payload, &first.message.content)?;
         Ok(first.message.content.clone())
     } else {
-        Err("Empty AI response".into())
+        Err(AppError::ServerError("Empty AI response".to_string()))
     }
 }
 
 #[tauri::command]
-async fn get_first_installed_preset(app: tauri::AppHandle) -> Result<Option<PackSource>, String> {
+async fn get_first_installed_preset(app: tauri::AppHandle) -> Result<Option<PackSource>, AppError> {
     const PACKS_JSON: &str = include_str!("../pack-sources.json");
     let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
     for p in packs {
@@ -1240,65 +4250,421 @@ async fn get_first_installed_preset(app: tauri::AppHandle) -> Result<Option<Pack
     Ok(None)
 }
 
+#[derive(Serialize)]
+struct ScannedModel {
+    preset_id: String,
+    path: String,
+    /// A pack's file found elsewhere and moved to `<preset id>/`
+    relocated: bool,
+}
+
+#[derive(Serialize)]
+struct SkippedModel {
+    path: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct ModelScan {
+    /// Files of known packs and models registered by an earlier scan
+    recognized: Vec<ScannedModel>,
+    /// Unknown GGUF files added as user presets by this scan
+    registered: Vec<ScannedModel>,
+    skipped: Vec<SkippedModel>,
+}
+
+/// Folder levels below the models root searched by `scan_models`
+const MODEL_SCAN_DEPTH: usize = 3;
+
+/// `.gguf` files under `root`, in a stable order
+fn find_gguf_files(root: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.is_dir() {
+                if depth < MODEL_SCAN_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+            } else if path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case("gguf"))
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Why a GGUF file can't be loaded as a model on its own, `None` if it can
+fn not_a_model(file_name: &str) -> Option<&'static str> {
+    let lower = file_name.to_ascii_lowercase();
+    if lower.starts_with("mmproj") {
+        return Some("Vision projector, not a model");
+    }
+    // Split models are loaded from their first shard (`-00001-of-0000N`)
+    let bytes = lower.as_bytes();
+    let later_shard = lower.find("-of-").is_some_and(|i| {
+        i >= 6
+            && bytes[i - 6] == b'-'
+            && bytes[i - 5..i].iter().all(u8::is_ascii_digit)
+            && bytes[i - 5..i] != *b"00001"
+    });
+    if later_shard {
+        return Some("Later shard of a split model");
+    }
+    None
+}
+
+/// Preset id for a user model, unique among `taken`
+fn user_preset_id(label: &str, taken: &HashSet<String>) -> String {
+    let slug: String = label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let base = format!("user_{}", slug.trim_matches('_'));
+    let mut id = base.clone();
+    let mut n = 2;
+    while taken.contains(&id) {
+        id = format!("{}_{}", base, n);
+        n += 1;
+    }
+    id
+}
+
+/// Register GGUF files already in the models folder
+///
+/// Files named like a pack's model count as that pack (moved into its
+/// folder when that one is empty); any other valid GGUF becomes a user
+/// preset listed by `get_presets`.
+#[tauri::command]
+async fn scan_models(app: AppHandle, db: State<'_, DbState>) -> Result<ModelScan, AppError> {
+    const PACKS_JSON: &str = include_str!("../pack-sources.json");
+    let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
+    let root = models_root_dir(&app)?;
+    let user_presets = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        db::list_user_presets(&conn)?
+    };
+    let mut taken: HashSet<String> = packs
+        .iter()
+        .map(|p| p.id.clone())
+        .chain(user_presets.iter().map(|p| p.id.clone()))
+        .collect();
+
+    let mut scan = ModelScan {
+        recognized: Vec::new(),
+        registered: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let display = |p: &Path| p.to_string_lossy().to_string();
+    for path in find_gguf_files(&root) {
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Ok(rel) = path.strip_prefix(&root) else {
+            continue;
+        };
+        let rel_path = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let skip = |reason: &str| SkippedModel {
+            path: display(&path),
+            reason: reason.to_string(),
+        };
+
+        if let Some(pack) = packs.iter().find(|p| p.filename == file_name) {
+            let expected = root.join(&pack.id).join(&pack.filename);
+            if path == expected {
+                scan.recognized.push(ScannedModel {
+                    preset_id: pack.id.clone(),
+                    path: display(&path),
+                    relocated: false,
+                });
+            } else if expected.exists() {
+                scan.skipped.push(skip("Copy of an installed pack"));
+            } else {
+                // Same volume (both under the models root), so this is a cheap rename
+                let moved = fs::create_dir_all(root.join(&pack.id))
+                    .and_then(|_| fs::rename(&path, &expected));
+                match moved {
+                    Ok(()) => scan.recognized.push(ScannedModel {
+                        preset_id: pack.id.clone(),
+                        path: display(&expected),
+                        relocated: true,
+                    }),
+                    Err(e) => scan
+                        .skipped
+                        .push(skip(&format!("Failed to move into {}: {}", pack.id, e))),
+                }
+            }
+            continue;
+        }
+        if let Some(p) = user_presets.iter().find(|p| p.rel_path == rel_path) {
+            scan.recognized.push(ScannedModel {
+                preset_id: p.id.clone(),
+                path: display(&path),
+                relocated: false,
+            });
+            continue;
+        }
+        if let Some(reason) = not_a_model(file_name) {
+            scan.skipped.push(skip(reason));
+            continue;
+        }
+        if let Err(e) = gguf::validate_header(&path) {
+            scan.skipped.push(skip(&e));
+            continue;
+        }
+
+        let label = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.to_string());
+        let id = user_preset_id(&label, &taken);
+        let size_bytes = fs::metadata(&path).ok().map(|m| m.len() as i64);
+        {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            db::add_user_preset(&conn, &id, &label, &rel_path, size_bytes)?;
+        }
+        taken.insert(id.clone());
+        scan.registered.push(ScannedModel {
+            preset_id: id,
+            path: display(&path),
+            relocated: false,
+        });
+    }
+    Ok(scan)
+}
+
+/// Absolute path of a preset's installed model, checked to be a GGUF file
+fn preset_model_path(app: &AppHandle, preset_id: &str) -> Result<PathBuf, AppError> {
+    const PACKS_JSON: &str = include_str!("../pack-sources.json");
+    let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
+    let rel_path = match packs.into_iter().find(|p| p.id == preset_id) {
+        Some(pack) => Path::new(&pack.id).join(&pack.filename),
+        None => {
+            let user_preset = app.try_state::<DbState>().and_then(|db| {
+                let conn = db.0.lock().ok()?;
+                db::get_user_preset(&conn, preset_id).ok().flatten()
+            });
+            user_preset
+                .map(|p| PathBuf::from(p.rel_path))
+                .ok_or_else(|| AppError::UnknownPreset(format!("Unknown preset: {}", preset_id)))?
+        }
+    };
+    let model_path = models_root_dir(app)?.join(rel_path);
+    if !model_path.exists() {
+        return Err(AppError::ModelNotFound(format!(
+            "Model not found: {}",
+            model_path.display()
+        )));
+    }
+    gguf::validate_header(&model_path).map_err(AppError::InvalidModel)?;
+    Ok(model_path)
+}
+
 #[tauri::command]
 async fn start_llama_with_preset(
     preset_id: String,
     window: Window,
     app: tauri::AppHandle,
-) -> Result<u32, String> {
+) -> Result<u32, AppError> {
+    let model_path = preset_model_path(&app, &preset_id)?;
+    // Pass absolute path to avoid base-dir ambiguity
+    let model_path_str = model_path.to_string_lossy().to_string();
+    start_server(model_path_str, default_ctx_size(&app), window, &app)
+}
+
+#[derive(Serialize)]
+struct RunnablePreset {
+    id: String,
+    filename: String,
+    size_bytes: Option<u64>,
+    /// Model file present on disk
+    installed: bool,
+    /// Model of the llama-server we're running
+    loaded: bool,
+}
+
+/// Every pack with whether it's installed and whether it's the loaded model
+#[tauri::command]
+async fn list_runnable_presets(app: AppHandle) -> Result<Vec<RunnablePreset>, AppError> {
     const PACKS_JSON: &str = include_str!("../pack-sources.json");
     let packs: Vec<PackSource> = serde_json::from_str(PACKS_JSON).map_err(|e| e.to_string())?;
-    let pack = packs
+    let models_dir = models_root_dir(&app)?;
+    let loaded_path = llama_install::running_server_launch().map(|l| PathBuf::from(l.model_path));
+    Ok(packs
         .into_iter()
-        .find(|p| p.id == preset_id)
-        .ok_or_else(|| "Unknown preset".to_string())?;
-    let model_path = models_root_dir(&app)?.join(&pack.id).join(&pack.filename);
-    if !model_path.exists() {
-        return Err(format!("Model not found: {}", model_path.display()));
+        .map(|pack| {
+            let path = models_dir.join(&pack.id).join(&pack.filename);
+            RunnablePreset {
+                installed: path.is_file(),
+                loaded: loaded_path.as_deref() == Some(path.as_path()),
+                id: pack.id,
+                filename: pack.filename,
+                size_bytes: pack.size_bytes,
+            }
+        })
+        .collect())
+}
+
+/// Make `preset_id` the loaded model, restarting llama-server only if needed
+///
+/// Returns the server PID. Nothing is restarted when that model already
+/// runs with the requested context size (any size when `ctx_size` is unset).
+/// Generations still streaming from the previous server are interrupted.
+#[tauri::command]
+async fn switch_to_preset(
+    preset_id: String,
+    ctx_size: Option<i32>,
+    window: Window,
+    app: AppHandle,
+) -> Result<u32, AppError> {
+    if matches!(ctx_size, Some(n) if n <= 0) {
+        return Err(AppError::InvalidInput(
+            "Context size must be positive".to_string(),
+        ));
     }
-    // Pass absolute path to avoid base-dir ambiguity
-    let model_path_str = model_path.to_string_lossy().to_string();
-    llama_install::start_server_process(model_path_str, 2048, window, &app)
+    let model_path = preset_model_path(&app, &preset_id)?;
+    if let Some(launch) = llama_install::running_server_launch() {
+        let same_ctx = ctx_size.unwrap_or(launch.ctx_size) == launch.ctx_size;
+        if Path::new(&launch.model_path) == model_path && same_ctx {
+            return Ok(launch.pid);
+        }
+        llama_install::stop_server_process(window.clone())?;
+    }
+    let ctx_size = ctx_size.unwrap_or_else(|| default_ctx_size(&app));
+    start_server(
+        model_path.to_string_lossy().to_string(),
+        ctx_size,
+        window,
+        &app,
+    )
+}
+
+/// Start llama-server, reporting a missing binary as `ServerNotInstalled`
+fn start_server(
+    model_path: String,
+    ctx_size: i32,
+    window: Window,
+    app: &AppHandle,
+) -> Result<u32, AppError> {
+    if !llama_install::get_server_binary_path(app)?.exists() {
+        return Err(AppError::ServerNotInstalled(
+            "llama-server binary not found. Please install it first.".to_string(),
+        ));
+    }
+    llama_install::start_server_process(model_path, ctx_size, window, app).map_err(AppError::from)
 }
 
 #[tauri::command]
-async fn download_llama_server(window: Window, app: tauri::AppHandle) -> Result<String, String> {
+async fn download_llama_server(window: Window, app: tauri::AppHandle) -> Result<String, AppError> {
+    // Release size is unknown before the request, check against a generous estimate
+    disk::ensure_free_space(
+        &llama_install::get_base_dir()?,
+        llama_install::SERVER_DOWNLOAD_ESTIMATE_BYTES,
+    )?;
+
     // Download binary
-    let zip_path = llama_install::download_server_binary(window.clone()).await?;
+    let zip_path = llama_install::download_server_binary(window.clone())
+        .await
+        .map_err(AppError::DownloadFailed)?;
 
     // Extract binary
     let binary_path = llama_install::extract_server_binary(&zip_path, &app)?;
 
+    // Cleanup temp file
+    fs::remove_file(&zip_path).ok();
+
+    window.emit("llama-server-status", "installed").ok();
+
+    Ok(binary_path.to_string_lossy().to_string())
+}
+
+/// Install llama-server from a release archive obtained elsewhere (offline/air-gapped machines)
+#[tauri::command]
+async fn install_server_from_zip(
+    local_zip_path: String,
+    window: Window,
+    app: tauri::AppHandle,
+) -> Result<String, AppError> {
+    let zip_path = PathBuf::from(&local_zip_path);
+    if !zip_path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "Archive not found: {}",
+            local_zip_path
+        )));
+    }
+
+    llama_install::validate_server_archive(&zip_path).map_err(AppError::InvalidInput)?;
+
+    window.emit("llama-server-status", "extracting").ok();
+    // The user's archive is left in place
+    let binary_path = llama_install::extract_server_binary(&zip_path, &app)?;
+
     window.emit("llama-server-status", "installed").ok();
 
     Ok(binary_path.to_string_lossy().to_string())
 }
 
+/// Context size from settings, 2048 when the database isn't available
+fn default_ctx_size(app: &AppHandle) -> i32 {
+    app.try_state::<DbState>()
+        .and_then(|db| {
+            let conn = db.0.lock().ok()?;
+            settings::get_i64(&conn, settings::DEFAULT_CTX_SIZE)
+        })
+        .and_then(|n| i32::try_from(n).ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(2048)
+}
+
+/// Report which release archive to fetch manually for an offline install
+#[tauri::command]
+async fn get_server_release_info() -> Result<llama_install::ReleaseInfo, AppError> {
+    llama_install::get_release_info().map_err(AppError::from)
+}
+
 #[tauri::command]
 async fn start_llama_server(
     model_path: String,
     ctx_size: Option<i32>,
     window: Window,
     app: tauri::AppHandle,
-) -> Result<u32, String> {
-    let context_size = ctx_size.unwrap_or(2048);
-    llama_install::start_server_process(model_path, context_size, window, &app)
+) -> Result<u32, AppError> {
+    let context_size = ctx_size.unwrap_or_else(|| default_ctx_size(&app));
+    start_server(model_path, context_size, window, &app)
 }
 
 #[tauri::command]
-async fn stop_llama_server(window: Window) -> Result<(), String> {
-    llama_install::stop_server_process(window)
+async fn stop_llama_server(window: Window) -> Result<(), AppError> {
+    llama_install::stop_server_process(window).map_err(AppError::from)
 }
 
 // ============= LOGS & DIAGNOSTICS =============
 
 #[tauri::command]
-async fn get_llama_logs() -> Result<Vec<String>, String> {
+async fn get_llama_logs() -> Result<Vec<String>, AppError> {
     Ok(llama_install::get_logs_snapshot())
 }
 
 #[tauri::command]
-async fn clear_llama_logs() -> Result<(), String> {
+async fn clear_llama_logs() -> Result<(), AppError> {
     llama_install::clear_logs();
     Ok(())
 }
@@ -1310,8 +4676,156 @@ struct ServerDiagnostics {
     env_path_head: Option<String>,
 }
 
+#[derive(Serialize)]
+struct AppPaths {
+    base_dir: String,
+    db_path: String,
+    models_dir: String,
+    llama_bin_dir: String,
+    /// Where pre-migration versions stored data
+    exe_dir: Option<String>,
+    /// Folders moved from `exe_dir` during this launch
+    migrated: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DbFileSizes {
+    db_bytes: u64,
+    /// Size of the `-wal` file, 0 when absent
+    wal_bytes: u64,
+}
+
+fn db_file_sizes(db_path: &Path) -> DbFileSizes {
+    let size = |p: &Path| fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    let mut wal = db_path.as_os_str().to_owned();
+    wal.push("-wal");
+    DbFileSizes {
+        db_bytes: size(db_path),
+        wal_bytes: size(Path::new(&wal)),
+    }
+}
+
+#[derive(Serialize)]
+struct MaintenanceReport {
+    before: DbFileSizes,
+    after: DbFileSizes,
+    conversations: i64,
+    messages: i64,
+    /// False when the WAL couldn't be fully checkpointed (it is retried on the next run)
+    wal_checkpointed: bool,
+}
+
+/// VACUUM the database and truncate its WAL, reporting storage usage
+///
+/// Runs on the shared connection under the DB lock, so no other command
+/// can have a statement or transaction open on it meanwhile.
+#[tauri::command]
+async fn database_maintenance(
+    app: AppHandle,
+    db: State<'_, DbState>,
+) -> Result<MaintenanceReport, AppError> {
+    let db_path = db::get_db_path(&app)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let before = db_file_sizes(&db_path);
+    let wal_checkpointed = db::vacuum(&conn)?;
+    let (conversations, messages) = db::count_rows(&conn)?;
+    drop(conn);
+    Ok(MaintenanceReport {
+        before,
+        after: db_file_sizes(&db_path),
+        conversations,
+        messages,
+        wal_checkpointed,
+    })
+}
+
+/// Save every conversation, message and setting into one backup archive at `dest_path`
+#[tauri::command]
+async fn export_all(
+    dest_path: String,
+    db: State<'_, DbState>,
+) -> Result<backup::BackupManifest, AppError> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    backup::export_all(&conn, Path::new(&dest_path))
+}
+
+#[derive(Serialize)]
+struct ImportReport {
+    conversations: i64,
+    messages: i64,
+    /// Presets used by the imported conversations whose model isn't installed here
+    missing_models: Vec<String>,
+}
+
+/// Replace all data with an `export_all` archive (the previous database is kept as `.bak`)
+#[tauri::command]
+async fn import_all(
+    src_path: String,
+    app: AppHandle,
+    db: State<'_, DbState>,
+) -> Result<ImportReport, AppError> {
+    let db_path = db::get_db_path(&app)?;
+    let (_manifest, staged) = backup::stage_import(Path::new(&src_path), &db_path)?;
+
+    let (conversations, messages, preset_ids) = {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        // Points into the other machine's filesystem, keep this machine's choice
+        let models_dir =
+            settings::get(&conn, settings::MODELS_DIR)?.unwrap_or(serde_json::Value::Null);
+
+        // Replacing the connection closes the file so it can be moved
+        *conn = Connection::open_in_memory()?;
+        if let Err(e) = backup::swap_database(&staged, &db_path) {
+            let _ = fs::remove_file(&staged);
+            *conn = db::init_db(&app)?;
+            return Err(e);
+        }
+        match db::init_db(&app) {
+            Ok(imported) => *conn = imported,
+            Err(e) => {
+                // Back to the previous data rather than an empty in-memory database
+                let restored = backup::restore_previous(&db_path);
+                *conn = db::init_db(&app)?;
+                restored?;
+                return Err(e.into());
+            }
+        }
+
+        settings::set(&conn, settings::MODELS_DIR, &models_dir)?;
+        let (conversations, messages) = db::count_rows(&conn)?;
+        (conversations, messages, db::conversation_preset_ids(&conn)?)
+    };
+
+    // Outside the lock: resolving the models folder reads settings
+    let missing_models = preset_ids
+        .into_iter()
+        .filter(|id| preset_model_path(&app, id).is_err())
+        .collect();
+    Ok(ImportReport {
+        conversations,
+        messages,
+        missing_models,
+    })
+}
+
+/// Resolved storage locations, to verify the app-data migration
+#[tauri::command]
+fn get_app_paths(app: AppHandle) -> Result<AppPaths, AppError> {
+    let base = paths::base_dir()?;
+    Ok(AppPaths {
+        base_dir: base.to_string_lossy().to_string(),
+        db_path: db::get_db_path(&app)?.to_string_lossy().to_string(),
+        models_dir: models_root_dir(&app)?.to_string_lossy().to_string(),
+        llama_bin_dir: base.join("llama-bin").to_string_lossy().to_string(),
+        exe_dir: paths::exe_dir()
+            .ok()
+            .map(|p| p.to_string_lossy().to_string()),
+        migrated: paths::migrated_dirs(),
+    })
+}
+
 #[tauri::command]
-async fn get_server_diagnostics(app: AppHandle) -> Result<ServerDiagnostics, String> {
+async fn get_server_diagnostics(app: AppHandle) -> Result<ServerDiagnostics, AppError> {
     let status = llama_install::check_server_binary(&app)?;
     let bin_dir = status.path.as_ref().and_then(|p| {
         std::path::Path::new(p)
@@ -1327,3 +4841,260 @@ async fn get_server_diagnostics(app: AppHandle) -> Result<ServerDiagnostics, Str
         env_path_head,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn questions(list: &[&str]) -> DialogueResult {
+        DialogueResult::Questions {
+            questions: list.iter().map(|q| q.to_string()).collect(),
+        }
+    }
+
+    fn final_prompt(prompt: &str) -> DialogueResult {
+        DialogueResult::Final {
+            prompt: prompt.to_string(),
+        }
+    }
+
+    #[test]
+    fn one_generation_per_conversation() {
+        let generations = GenerationManager::default();
+        let first = generations.register("a", Some(1)).unwrap();
+        assert!(matches!(
+            generations.register("b", Some(1)),
+            Err(AppError::AlreadyGenerating(_))
+        ));
+        // Other conversations and chat-less generations aren't blocked
+        let _other = generations.register("c", Some(2)).unwrap();
+        let _free = generations.register("d", None).unwrap();
+        assert!(matches!(
+            generations.register("d", None),
+            Err(AppError::AlreadyGenerating(_))
+        ));
+
+        drop(first);
+        assert!(generations.register("b", Some(1)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn open_part_file_reports_unusable_paths() {
+        let dir = std::env::temp_dir().join(format!("whytchat-part-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // A file where the models folder should be
+        let blocker = dir.join("models");
+        fs::write(&blocker, b"").unwrap();
+        let part_path = blocker.join("model.gguf.part");
+
+        // Fresh download and resume alike
+        for resume in [0, 10] {
+            let error = open_part_file(&part_path, resume).await.unwrap_err();
+            assert!(error.starts_with("Failed to open"), "{}", error);
+            assert!(error.contains("model.gguf.part"), "{}", error);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn preset_ids_cannot_escape_the_models_dir() {
+        for bad in [
+            "../x",
+            "..",
+            ".",
+            "a/b",
+            "a\\b",
+            "",
+            "/etc/passwd",
+            "C:\\models",
+            "x y",
+        ] {
+            assert!(!is_valid_preset_id(bad), "{}", bad);
+        }
+        assert!(is_valid_preset_id("my-model_2"));
+        // Known packs are accepted even with characters custom ids can't use
+        assert!(is_valid_preset_id("qwen_coder_1.5b_light"));
+        assert!(!is_valid_preset_id("my_model_1.5b"));
+    }
+
+    #[test]
+    fn parse_content_range_values() {
+        assert_eq!(
+            parse_content_range("bytes 100-199/1000"),
+            Some((Some(100), Some(1000)))
+        );
+        assert_eq!(parse_content_range("bytes 0-99/*"), Some((Some(0), None)));
+        assert_eq!(
+            parse_content_range("bytes */1000"),
+            Some((None, Some(1000)))
+        );
+        for malformed in [
+            "",
+            "bytes",
+            "100-199/1000",
+            "items 100-199/1000",
+            "bytes 100-199",
+            "bytes 100/1000",
+            "bytes abc-199/1000",
+            "bytes 100-199/abc",
+            "bytes 200-100/1000",
+        ] {
+            assert_eq!(parse_content_range(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn resume_decisions() {
+        use reqwest::StatusCode;
+        let resume = 100;
+        assert_eq!(
+            resume_decision(
+                StatusCode::PARTIAL_CONTENT,
+                Some((Some(100), Some(1000))),
+                resume
+            ),
+            ResumeDecision::Append
+        );
+        // Resumed somewhere else than asked
+        assert_eq!(
+            resume_decision(
+                StatusCode::PARTIAL_CONTENT,
+                Some((Some(0), Some(1000))),
+                resume
+            ),
+            ResumeDecision::Refetch
+        );
+        assert_eq!(
+            resume_decision(StatusCode::PARTIAL_CONTENT, None, resume),
+            ResumeDecision::Refetch
+        );
+        // 416: done if the .part is exactly the remote size, changed otherwise
+        assert_eq!(
+            resume_decision(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                Some((None, Some(100))),
+                resume
+            ),
+            ResumeDecision::Complete
+        );
+        assert_eq!(
+            resume_decision(
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                Some((None, Some(50))),
+                resume
+            ),
+            ResumeDecision::Refetch
+        );
+        assert_eq!(
+            resume_decision(StatusCode::RANGE_NOT_SATISFIABLE, None, resume),
+            ResumeDecision::Refetch
+        );
+        assert_eq!(
+            resume_decision(StatusCode::OK, None, resume),
+            ResumeDecision::FromStart
+        );
+        assert_eq!(
+            resume_decision(StatusCode::NOT_FOUND, None, resume),
+            ResumeDecision::FromStart
+        );
+    }
+
+    #[test]
+    fn finished_dialogue_round_keeps_the_others_cancelable() {
+        let dialogue = PromptDialogueState::default();
+        let (first, _) = dialogue.register().unwrap();
+        let (_second, registration) = dialogue.register().unwrap();
+        let second = Abortable::new(std::future::pending::<()>(), registration);
+
+        drop(first);
+        assert_eq!(dialogue.cancel_all().unwrap(), 1);
+        assert!(second.is_aborted());
+        assert_eq!(dialogue.cancel_all().unwrap(), 0);
+    }
+
+    #[test]
+    fn marker_ends_tolerates_markdown() {
+        let text = "**PROMPT_FINAL:** Write a haiku";
+        assert_eq!(marker_ends(text, &FINAL_MARKERS), vec![17]);
+        let text = "## Questions :\n1. Why?";
+        assert_eq!(marker_ends(text, &QUESTIONS_MARKERS), vec![14]);
+        assert!(marker_ends("questions about nothing", &QUESTIONS_MARKERS).is_empty());
+    }
+
+    #[test]
+    fn clean_question_line_strips_bullets_and_numbers() {
+        assert_eq!(clean_question_line("  - What tone?"), "What tone?");
+        assert_eq!(clean_question_line("* What tone?"), "What tone?");
+        assert_eq!(clean_question_line("• What tone?"), "What tone?");
+        assert_eq!(clean_question_line("2. What tone?"), "What tone?");
+        assert_eq!(clean_question_line("3) What tone?"), "What tone?");
+        assert_eq!(clean_question_line("2024 budget?"), "2024 budget?");
+    }
+
+    #[test]
+    fn parses_markdown_final_marker() {
+        assert_eq!(
+            parse_dialogue_reply("**PROMPT_FINAL:** Write a haiku about rain"),
+            final_prompt("Write a haiku about rain")
+        );
+    }
+
+    #[test]
+    fn parses_markdown_questions_marker() {
+        assert_eq!(
+            parse_dialogue_reply("## Questions :\n1. Who is it for?\n2. How long?"),
+            questions(&["Who is it for?", "How long?"])
+        );
+    }
+
+    #[test]
+    fn ignores_code_fences() {
+        let reply = "```markdown\nPROMPT_FINAL:\nSummarize the article\n```";
+        assert_eq!(
+            parse_dialogue_reply(reply),
+            final_prompt("Summarize the article")
+        );
+    }
+
+    #[test]
+    fn matches_lowercase_markers() {
+        assert_eq!(
+            parse_dialogue_reply("prompt final: Translate to German"),
+            final_prompt("Translate to German")
+        );
+        assert_eq!(
+            parse_dialogue_reply("questions:\n- Formal or casual?"),
+            questions(&["Formal or casual?"])
+        );
+    }
+
+    #[test]
+    fn skips_preamble_before_marker() {
+        let reply = "Sure! Here are my questions:\nQUESTIONS:\n- Which audience?";
+        assert_eq!(parse_dialogue_reply(reply), questions(&["Which audience?"]));
+        let reply = "Thanks, that's everything.\nPROMPT_FINAL: Draft the email";
+        assert_eq!(parse_dialogue_reply(reply), final_prompt("Draft the email"));
+    }
+
+    #[test]
+    fn final_marker_wins_over_questions() {
+        let reply = "QUESTIONS:\n- Anything else?\nPROMPT_FINAL: List three questions: a, b, c";
+        assert_eq!(
+            parse_dialogue_reply(reply),
+            final_prompt("List three questions: a, b, c")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_a_single_question() {
+        assert_eq!(
+            parse_dialogue_reply("  What should the prompt achieve?\n"),
+            questions(&["What should the prompt achieve?"])
+        );
+        // A marker with nothing after it is no answer either
+        assert_eq!(
+            parse_dialogue_reply("PROMPT_FINAL:"),
+            questions(&["PROMPT_FINAL:"])
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::error::AppError;
+use tauri::Window;
+
+/// Lowest opacity accepted, below this the overlay is practically invisible
+pub const MIN_OPACITY: f64 = 0.2;
+pub const MAX_OPACITY: f64 = 1.0;
+
+/// Apply opacity from any thread (native window calls are dispatched to the main thread)
+///
+/// Must not be called from the main thread itself, use `apply_on_main_thread` there.
+pub fn apply(window: &Window, level: f64) -> Result<(), AppError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || {
+            let _ = tx.send(apply_on_main_thread(&target, level));
+        })
+        .map_err(|e| AppError::Other(e.to_string()))?;
+    rx.recv()
+        .map_err(|_| AppError::Other("Window closed before opacity was applied".to_string()))?
+}
+
+#[cfg(target_os = "windows")]
+pub fn apply_on_main_thread(window: &Window, level: f64) -> Result<(), AppError> {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetWindowLongW, SetLayeredWindowAttributes, SetWindowLongW, GWL_EXSTYLE, LWA_ALPHA,
+        WS_EX_LAYERED,
+    };
+
+    let hwnd = window.hwnd().map_err(|e| AppError::Other(e.to_string()))?.0 as _;
+    let alpha = (level * 255.0).round() as u8;
+    // SAFETY: hwnd is the live handle of this window and we are on its UI thread
+    let ok = unsafe {
+        let style = GetWindowLongW(hwnd, GWL_EXSTYLE);
+        SetWindowLongW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED as i32);
+        SetLayeredWindowAttributes(hwnd, 0, alpha, LWA_ALPHA)
+    };
+    if ok == 0 {
+        return Err(AppError::Other(
+            "SetLayeredWindowAttributes failed".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn apply_on_main_thread(window: &Window, level: f64) -> Result<(), AppError> {
+    use gtk::prelude::WidgetExt;
+
+    // Only visible with a compositing window manager, GTK ignores it otherwise
+    window
+        .gtk_window()
+        .map_err(|e| AppError::Other(e.to_string()))?
+        .set_opacity(level);
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn apply_on_main_thread(_window: &Window, _level: f64) -> Result<(), AppError> {
+    Err(AppError::Unsupported(
+        "Window opacity is not supported on this platform".to_string(),
+    ))
+}
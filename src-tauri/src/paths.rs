@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+/// Per-user data directory resolved at startup (production only)
+static DATA_ROOT: OnceLock<PathBuf> = OnceLock::new();
+/// Directories moved by `migrate_from_exe_dir`, reported by diagnostics
+static MIGRATED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Folders that used to live next to the executable
+const LEGACY_DIRS: [&str; 3] = ["data", "models", "llama-bin"];
+
+/// Root holding `data/`, `models/` and `llama-bin/`
+///
+/// Dev keeps everything in the project root; production uses the OS app-data
+/// directory so locked-down install paths and reinstalls don't lose data.
+pub fn base_dir() -> Result<PathBuf, String> {
+    if cfg!(debug_assertions) {
+        let src_tauri = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        Ok(src_tauri
+            .parent()
+            .ok_or("src-tauri has no parent")?
+            .to_path_buf())
+    } else {
+        DATA_ROOT
+            .get()
+            .cloned()
+            .ok_or_else(|| "App data directory not initialized".to_string())
+    }
+}
+
+/// Directory of the executable, where older versions stored everything
+pub fn exe_dir() -> Result<PathBuf, String> {
+    Ok(std::env::current_exe()
+        .map_err(|e| format!("Failed to get exe path: {}", e))?
+        .parent()
+        .ok_or("No parent directory for exe")?
+        .to_path_buf())
+}
+
+/// Resolve the app-data directory; must run before anything touches the DB or models
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    if cfg!(debug_assertions) {
+        return Ok(());
+    }
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let _ = DATA_ROOT.set(dir);
+    Ok(())
+}
+
+/// One-time move of `data/`, `models/` and `llama-bin/` from the exe directory
+///
+/// A folder is only moved when it doesn't exist yet at the destination, so
+/// this is a no-op on every launch after the first.
+pub fn migrate_from_exe_dir() {
+    if cfg!(debug_assertions) {
+        return;
+    }
+    let (Ok(from), Ok(to)) = (exe_dir(), base_dir()) else {
+        return;
+    };
+    if from == to {
+        return;
+    }
+    for name in LEGACY_DIRS {
+        let src = from.join(name);
+        let dst = to.join(name);
+        if !src.is_dir() || dst.exists() {
+            continue;
+        }
+        match move_dir(&src, &dst) {
+            Ok(()) => {
+                eprintln!("[paths] Migrated {:?} -> {:?}", src, dst);
+                if let Ok(mut m) = MIGRATED.lock() {
+                    m.push(name.to_string());
+                }
+            }
+            Err(e) => eprintln!("[paths] Failed to migrate {:?}: {}", src, e),
+        }
+    }
+}
+
+pub fn migrated_dirs() -> Vec<String> {
+    MIGRATED.lock().map(|m| m.clone()).unwrap_or_default()
+}
+
+/// Rename, falling back to copy + delete across volumes
+fn move_dir(src: &Path, dst: &Path) -> Result<(), String> {
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = copy_dir(src, dst) {
+        let _ = fs::remove_dir_all(dst);
+        return Err(e);
+    }
+    fs::remove_dir_all(src).map_err(|e| format!("Copied but failed to remove source: {}", e))
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<(), String> {
+    fs::create_dir_all(dst).map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(src).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let target = dst.join(entry.file_name());
+        if entry.file_type().map_err(|e| e.to_string())?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Id of the shipped template, used when none (or a missing one) is selected
+pub const DEFAULT_ID: &str = "default";
+
+const BUILTIN_JSON: &str = include_str!("../prompt-templates.json");
+
+/// Meta-prompts used by `generate_prompt_ai` and the prompt dialogue
+///
+/// Placeholders: `{strict}` and `{language}` in the system prompts,
+/// `{objective}` and `{clarifications}` in `generate_user`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    /// `{strict}` of `generate_system` when strict mode is on
+    pub strict_rules: String,
+    pub generate_system: String,
+    pub generate_user: String,
+    /// Starts `{clarifications}`, followed by one `- question answer` line each
+    pub clarifications_header: String,
+    /// `{strict}` of `dialogue_system` when strict mode is on
+    pub dialogue_strict_rules: String,
+    pub dialogue_system: String,
+    /// User turn sent when the dialogue starts without history
+    pub dialogue_opening: String,
+}
+
+/// Folder scanned for user templates, one JSON object per `*.json` file
+pub fn user_dir() -> Result<PathBuf, String> {
+    Ok(crate::paths::base_dir()?.join("prompt-templates"))
+}
+
+fn builtin() -> Result<Vec<PromptTemplate>, AppError> {
+    serde_json::from_str(BUILTIN_JSON).map_err(|e| AppError::Other(e.to_string()))
+}
+
+/// Valid user templates, invalid files and ids clashing with a built-in are skipped
+fn user_templates(taken: &[String]) -> Vec<PromptTemplate> {
+    let Ok(entries) = user_dir().and_then(|dir| fs::read_dir(dir).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let mut templates: Vec<PromptTemplate> = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| {
+                serde_json::from_str::<PromptTemplate>(&raw).map_err(|e| e.to_string())
+            });
+        match parsed {
+            Ok(t) if taken.contains(&t.id) || templates.iter().any(|o| o.id == t.id) => {
+                eprintln!(
+                    "[prompt_templates] Skipping {}: id '{}' already used",
+                    path.display(),
+                    t.id
+                );
+            }
+            Ok(t) => templates.push(t),
+            Err(e) => eprintln!("[prompt_templates] Skipping {}: {}", path.display(), e),
+        }
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+/// Built-in templates first, then the user's, with whether each is built-in
+pub fn list() -> Result<Vec<(PromptTemplate, bool)>, AppError> {
+    let builtin = builtin()?;
+    let ids: Vec<String> = builtin.iter().map(|t| t.id.clone()).collect();
+    let mut all: Vec<(PromptTemplate, bool)> = builtin.into_iter().map(|t| (t, true)).collect();
+    all.extend(user_templates(&ids).into_iter().map(|t| (t, false)));
+    Ok(all)
+}
+
+pub fn find(id: &str) -> Result<Option<PromptTemplate>, AppError> {
+    Ok(list()?.into_iter().map(|(t, _)| t).find(|t| t.id == id))
+}
+
+/// Template `id`, falling back to the default one when it no longer exists
+pub fn resolve(id: Option<&str>) -> Result<PromptTemplate, AppError> {
+    if let Some(id) = id {
+        match find(id)? {
+            Some(t) => return Ok(t),
+            None => eprintln!("[prompt_templates] '{}' not found, using default", id),
+        }
+    }
+    find(DEFAULT_ID)?
+        .ok_or_else(|| AppError::Other("Default prompt template is missing".to_string()))
+}
+
+/// Substitute `{name}` placeholders in one pass (values are never re-expanded)
+///
+/// Unknown placeholders are left as-is.
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let value = tail.find('}').and_then(|end| {
+            let name = &tail[1..end];
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| (*v, end + 1))
+        });
+        match value {
+            Some((v, len)) => {
+                out.push_str(v);
+                rest = &tail[len..];
+            }
+            None => {
+                out.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
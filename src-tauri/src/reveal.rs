@@ -0,0 +1,77 @@
+use crate::error::AppError;
+use std::path::Path;
+use std::process::Command;
+
+/// Open the OS file manager with `path` selected
+///
+/// `path` must already be canonical (absolute, no `..`).
+pub fn reveal(path: &Path) -> Result<(), AppError> {
+    platform_reveal(path).map_err(|e| {
+        AppError::Io(format!(
+            "Failed to open the file manager for {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn platform_reveal(path: &Path) -> std::io::Result<()> {
+    // `canonicalize` yields `\\?\C:\...`, which explorer doesn't understand
+    let display = path.to_string_lossy();
+    let plain = display.strip_prefix(r"\\?\").unwrap_or(&display);
+    // explorer exits with 1 even on success, so only spawning is checked
+    Command::new("explorer")
+        .arg(format!("/select,{}", plain))
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn platform_reveal(path: &Path) -> std::io::Result<()> {
+    Command::new("open").arg("-R").arg(path).spawn().map(|_| ())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn platform_reveal(path: &Path) -> std::io::Result<()> {
+    // freedesktop FileManager1 selects the item (Nautilus, Dolphin, Nemo, ...)
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(path)))
+        .arg("string:")
+        .output()
+        .is_ok_and(|out| out.status.success());
+    if shown {
+        return Ok(());
+    }
+    // No file manager service: open the containing folder instead
+    let folder = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    Command::new("xdg-open").arg(folder).spawn().map(|_| ())
+}
+
+/// `file://` URI with everything but unreserved characters and `/` percent-encoded
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut uri = String::from("file://");
+    for &b in path.as_os_str().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
@@ -0,0 +1,112 @@
+use crate::db;
+use rusqlite::{Connection, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// Known keys of the app_settings table
+pub const PROXY_URL: &str = "proxy_url";
+pub const WINDOW_OPACITY: &str = "window_opacity";
+/// Bool, the close button hides the window to the tray instead of quitting
+pub const CLOSE_TO_TRAY: &str = "close_to_tray";
+/// Context size used when starting llama-server without an explicit one
+pub const DEFAULT_CTX_SIZE: &str = "default_ctx_size";
+/// Absolute directory holding downloaded models, unset means `<base dir>/models`
+pub const MODELS_DIR: &str = "models_dir";
+/// Id of the conversation the user last opened
+pub const LAST_CONVERSATION_ID: &str = "last_conversation_id";
+/// Id of the prompt-generation template, unset means the shipped one
+pub const PROMPT_TEMPLATE: &str = "prompt_template";
+/// Bool, strict rules apply to prompt generation and new conversations unless told otherwise
+pub const STRICT_MODE: &str = "strict_mode";
+/// Strict-rules text by ISO 639-1 code (`{"en": "..."}`), a missing language uses the template's
+pub const STRICT_RULES: &str = "strict_rules";
+
+/// Value used when a known key was never set
+pub fn default_value(key: &str) -> Option<Value> {
+    match key {
+        PROXY_URL => Some(Value::Null),
+        WINDOW_OPACITY => Some(json!(1.0)),
+        CLOSE_TO_TRAY => Some(json!(false)),
+        DEFAULT_CTX_SIZE => Some(json!(2048)),
+        LAST_CONVERSATION_ID => Some(Value::Null),
+        MODELS_DIR => Some(Value::Null),
+        PROMPT_TEMPLATE => Some(Value::Null),
+        STRICT_MODE => Some(json!(false)),
+        STRICT_RULES => Some(Value::Null),
+        _ => None,
+    }
+}
+
+/// Command that must write `key`, because it validates or applies the value
+pub fn dedicated_setter(key: &str) -> Option<&'static str> {
+    match key {
+        PROXY_URL => Some("set_proxy"),
+        WINDOW_OPACITY => Some("set_window_opacity"),
+        MODELS_DIR => Some("set_models_dir"),
+        PROMPT_TEMPLATE => Some("set_prompt_template"),
+        _ => None,
+    }
+}
+
+const KNOWN_KEYS: [&str; 9] = [
+    PROXY_URL,
+    WINDOW_OPACITY,
+    CLOSE_TO_TRAY,
+    DEFAULT_CTX_SIZE,
+    LAST_CONVERSATION_ID,
+    MODELS_DIR,
+    PROMPT_TEMPLATE,
+    STRICT_MODE,
+    STRICT_RULES,
+];
+
+/// Stored value, or the default for known keys
+///
+/// A stored value that isn't valid JSON is treated as absent.
+pub fn get(conn: &Connection, key: &str) -> Result<Option<Value>> {
+    let stored = db::get_setting(conn, key)?.and_then(|raw| serde_json::from_str(&raw).ok());
+    Ok(stored.or_else(|| default_value(key)))
+}
+
+pub fn set(conn: &Connection, key: &str, value: &Value) -> Result<()> {
+    db::set_setting(conn, key, &value.to_string())
+}
+
+/// Defaults for every known key, overridden by whatever is stored
+pub fn list(conn: &Connection) -> Result<HashMap<String, Value>> {
+    let mut all: HashMap<String, Value> = KNOWN_KEYS
+        .iter()
+        .filter_map(|k| default_value(k).map(|v| (k.to_string(), v)))
+        .collect();
+    for (key, raw) in db::list_settings(conn)? {
+        if let Ok(value) = serde_json::from_str(&raw) {
+            all.insert(key, value);
+        }
+    }
+    Ok(all)
+}
+
+pub fn get_bool(conn: &Connection, key: &str) -> bool {
+    get(conn, key)
+        .ok()
+        .flatten()
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+pub fn get_f64(conn: &Connection, key: &str) -> Option<f64> {
+    get(conn, key).ok().flatten().and_then(|v| v.as_f64())
+}
+
+pub fn get_i64(conn: &Connection, key: &str) -> Option<i64> {
+    get(conn, key).ok().flatten().and_then(|v| v.as_i64())
+}
+
+/// String setting, `None` when unset, null or empty
+pub fn get_string(conn: &Connection, key: &str) -> Option<String> {
+    get(conn, key)
+        .ok()
+        .flatten()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .filter(|s| !s.is_empty())
+}
@@ -0,0 +1,121 @@
+/// Incremental Server-Sent Events decoder
+///
+/// Bytes are buffered until a full line is available, so payloads (and
+/// UTF-8 sequences) split across network chunks are reassembled. An event
+/// is dispatched on a blank line; its `data` lines are joined with `\n`.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// Feed received bytes, returns the data of every event completed by them
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line[..line.len() - 1]);
+            if let Some(data) = self.process_line(line.trim_end_matches('\r')) {
+                events.push(data);
+            }
+        }
+        events
+    }
+
+    /// Flush what is left once the stream ends (a last event without its blank line)
+    pub fn finish(&mut self) -> Option<String> {
+        if !self.buffer.is_empty() {
+            let rest = std::mem::take(&mut self.buffer);
+            let line = String::from_utf8_lossy(&rest);
+            if let Some(data) = self.process_line(line.trim_end_matches('\r')) {
+                return Some(data);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        // Comment / keep-alive
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        // `event`, `id` and `retry` are not used by llama-server's completions
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<String> {
+        if self.data.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.data).join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SseDecoder;
+
+    #[test]
+    fn data_line_split_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"da").is_empty());
+        assert!(decoder.push(b"ta: {\"content\":").is_empty());
+        assert!(decoder.push(b"\"hi\"}\n").is_empty());
+        assert_eq!(decoder.push(b"\n"), vec!["{\"content\":\"hi\"}"]);
+    }
+
+    #[test]
+    fn data_without_space() {
+        let mut decoder = SseDecoder::default();
+        assert_eq!(decoder.push(b"data:x\n\ndata:  y\n\n"), vec!["x", " y"]);
+    }
+
+    #[test]
+    fn comments_are_ignored() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b": keep-alive\n\n").is_empty());
+        assert_eq!(decoder.push(b":ping\ndata: a\n\n"), vec!["a"]);
+    }
+
+    #[test]
+    fn multi_line_data_is_joined() {
+        let mut decoder = SseDecoder::default();
+        assert_eq!(
+            decoder.push(b"event: message\r\ndata: one\r\ndata: two\r\n\r\n"),
+            vec!["one\ntwo"]
+        );
+    }
+
+    #[test]
+    fn utf8_char_split_across_chunks() {
+        let bytes = "data: é€\n\n".as_bytes();
+        // Split inside the two-byte `é` and the three-byte `€`
+        let (first, rest) = bytes.split_at(7);
+        let (second, third) = rest.split_at(2);
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(first).is_empty());
+        assert!(decoder.push(second).is_empty());
+        assert_eq!(decoder.push(third), vec!["é€"]);
+    }
+
+    #[test]
+    fn finish_flushes_unterminated_event() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"data: a\ndata: [DO").is_empty());
+        assert!(decoder.push(b"NE]").is_empty());
+        assert_eq!(decoder.finish().as_deref(), Some("a\n[DONE]"));
+        assert_eq!(decoder.finish(), None);
+    }
+}
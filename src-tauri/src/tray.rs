@@ -0,0 +1,100 @@
+use tauri::{
+    menu::{Menu, MenuEvent, MenuItem},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    App, AppHandle, Manager, Window,
+};
+
+pub fn init(app: &App) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, "show", "Show WhytChat", true, None::<&str>)?;
+    let overlay = MenuItem::with_id(app, "toggle_overlay", "Toggle overlay", true, None::<&str>)?;
+    let start = MenuItem::with_id(
+        app,
+        "start_server",
+        "Start llama-server",
+        true,
+        None::<&str>,
+    )?;
+    let stop = MenuItem::with_id(app, "stop_server", "Stop llama-server", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(app, &[&show, &overlay, &start, &stop, &quit])?;
+
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip("WhytChat")
+        .menu(&menu)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+fn main_window(app: &AppHandle) -> Option<Window> {
+    app.get_webview_window("main").map(|w| w.as_ref().window())
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = main_window(app) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let Some(window) = main_window(app) else {
+        return;
+    };
+    match event.id().as_ref() {
+        "show" => show_main_window(app),
+        "toggle_overlay" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = app.state::<crate::OverlayState>();
+                if let Err(e) = crate::toggle_overlay(window, state).await {
+                    eprintln!("[tray] Toggle overlay failed: {}", e);
+                }
+            });
+        }
+        "start_server" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let preset = match crate::get_first_installed_preset(app.clone()).await {
+                    Ok(Some(p)) => p,
+                    Ok(None) => {
+                        eprintln!("[tray] No installed model to start");
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("[tray] {}", e);
+                        return;
+                    }
+                };
+                if let Err(e) = crate::start_llama_with_preset(preset.id, window, app).await {
+                    eprintln!("[tray] Start server failed: {}", e);
+                }
+            });
+        }
+        "stop_server" => {
+            if let Err(e) = crate::llama_install::stop_server_process(window) {
+                eprintln!("[tray] Stop server failed: {}", e);
+            }
+        }
+        "quit" => {
+            // Hidden windows still get Destroyed on exit, but don't rely on it
+            let _ = crate::llama_install::stop_server_process(window);
+            app.exit(0);
+        }
+        _ => {}
+    }
+}
@@ -47,6 +47,12 @@ type Message = {
   timestamp: Date;
 };
 
+type GenerationEvent = {
+  request_id: string;
+  conversation_id: number;
+  content: string;
+};
+
 type ChatProps = {
   conversationId?: string;
   onNavigate: (view: string) => void;
@@ -307,6 +313,7 @@ export function Chat({ conversationId, onNavigate }: ChatProps) {
     let unlistenChunk: UnlistenFn | null = null;
     let unlistenComplete: UnlistenFn | null = null;
     let unlistenError: UnlistenFn | null = null;
+    let unlistenTimeout: UnlistenFn | null = null;
 
     try {
       // Server is already started by useEffect
@@ -354,62 +361,102 @@ export function Chat({ conversationId, onNavigate }: ChatProps) {
       setGenStartAt(Date.now());
 
       // Listen for streaming chunks
-      unlistenChunk = await listen<string>("generation-chunk", (event) => {
-        const chunk = sanitizeLLM(event.payload || "");
-        lastGenContentRef.current += chunk;
-        setMessages((prev) =>
-          prev.map((msg) =>
-            msg.id === tempId ? { ...msg, content: msg.content + chunk } : msg
-          )
-        );
-      });
+      unlistenChunk = await listen<GenerationEvent>(
+        "generation-chunk",
+        (event) => {
+          if (event.payload.request_id !== tempId) return;
+          const chunk = sanitizeLLM(event.payload.content || "");
+          lastGenContentRef.current += chunk;
+          setMessages((prev) =>
+            prev.map((msg) =>
+              msg.id === tempId
+                ? { ...msg, content: msg.content + chunk }
+                : msg
+            )
+          );
+        }
+      );
 
       // Listen for completion
-      unlistenComplete = await listen<string>("generation-complete", () => {
-        if (!currentAbortController.signal.aborted) {
-          setIsLoading(false);
-        }
-        if (genStartAt) {
-          const durationSec = Math.max(0.01, (Date.now() - genStartAt) / 1000);
-          const content = lastGenContentRef.current || "";
-          const words = content.trim() ? content.trim().split(/\s+/).length : 0;
-          const tokens = Math.ceil(content.length / 4);
-          const speedWps = words > 0 ? words / durationSec : 0;
-          setLastStats({ words, tokens, durationSec, speedWps });
+      unlistenComplete = await listen<GenerationEvent>(
+        "generation-complete",
+        (event) => {
+          if (event.payload.request_id !== tempId) return;
+          if (!currentAbortController.signal.aborted) {
+            setIsLoading(false);
+          }
+          if (genStartAt) {
+            const durationSec = Math.max(
+              0.01,
+              (Date.now() - genStartAt) / 1000
+            );
+            const content = lastGenContentRef.current || "";
+            const words = content.trim()
+              ? content.trim().split(/\s+/).length
+              : 0;
+            const tokens = Math.ceil(content.length / 4);
+            const speedWps = words > 0 ? words / durationSec : 0;
+            setLastStats({ words, tokens, durationSec, speedWps });
+          }
+          // Cleanup listeners
+          if (unlistenChunk) unlistenChunk();
+          if (unlistenComplete) unlistenComplete();
+          if (unlistenError) unlistenError();
+          if (unlistenTimeout) unlistenTimeout();
+          abortControllerRef.current = null;
         }
-        // Cleanup listeners
-        if (unlistenChunk) unlistenChunk();
-        if (unlistenComplete) unlistenComplete();
-        if (unlistenError) unlistenError();
-        abortControllerRef.current = null;
-      });
+      );
 
       // Listen for errors
-      unlistenError = await listen<string>("generation-error", (event) => {
-        console.error("Generation error:", event.payload);
-        // Remove temporary message and show error
-        setMessages((prev) => prev.filter((msg) => msg.id !== tempId));
-        setMessages((prev) => [
-          ...prev,
-          {
-            id: `error-${Date.now()}`,
-            role: "assistant",
-            content: `Error: ${event.payload}`,
-            timestamp: new Date(),
-          },
-        ]);
-        setIsLoading(false);
-        // Cleanup listeners
-        if (unlistenChunk) unlistenChunk();
-        if (unlistenComplete) unlistenComplete();
-        if (unlistenError) unlistenError();
-        abortControllerRef.current = null;
-      });
+      unlistenError = await listen<GenerationEvent>(
+        "generation-error",
+        (event) => {
+          if (event.payload.request_id !== tempId) return;
+          console.error("Generation error:", event.payload.content);
+          // Remove temporary message and show error
+          setMessages((prev) => prev.filter((msg) => msg.id !== tempId));
+          setMessages((prev) => [
+            ...prev,
+            {
+              id: `error-${Date.now()}`,
+              role: "assistant",
+              content: `Error: ${event.payload.content}`,
+              timestamp: new Date(),
+            },
+          ]);
+          setIsLoading(false);
+          // Cleanup listeners
+          if (unlistenChunk) unlistenChunk();
+          if (unlistenComplete) unlistenComplete();
+          if (unlistenError) unlistenError();
+          if (unlistenTimeout) unlistenTimeout();
+          abortControllerRef.current = null;
+        }
+      );
+
+      // Server went quiet: keep the partial answer (already saved backend-side)
+      unlistenTimeout = await listen<GenerationEvent>(
+        "generation-timeout",
+        (event) => {
+          if (event.payload.request_id !== tempId) return;
+          console.warn("Generation timed out");
+          if (!event.payload.content) {
+            setMessages((prev) => prev.filter((msg) => msg.id !== tempId));
+          }
+          setIsLoading(false);
+          if (unlistenChunk) unlistenChunk();
+          if (unlistenComplete) unlistenComplete();
+          if (unlistenError) unlistenError();
+          if (unlistenTimeout) unlistenTimeout();
+          abortControllerRef.current = null;
+        }
+      );
 
       // Start generation (use message with file context)
       await invoke("generate_text", {
         conversationId: parseInt(conversationId),
         userMessage: messageWithContext,
+        options: { requestId: tempId },
       });
     } catch (err) {
       console.error("Failed to send message:", err);
@@ -418,6 +465,7 @@ export function Chat({ conversationId, onNavigate }: ChatProps) {
       if (unlistenChunk) unlistenChunk();
       if (unlistenComplete) unlistenComplete();
       if (unlistenError) unlistenError();
+      if (unlistenTimeout) unlistenTimeout();
       abortControllerRef.current = null;
     }
   };
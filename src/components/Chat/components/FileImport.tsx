@@ -1,8 +1,8 @@
 import { useState } from "react";
-import { open } from "@tauri-apps/plugin-dialog";
 import { invoke } from "@tauri-apps/api/core";
 import { FileText, Upload, X, Check, AlertCircle } from "lucide-react";
 import { i18n } from "../../../i18n";
+import { formatError } from "../../../utils/errors";
 
 interface ImportedFile {
   name: string;
@@ -26,37 +26,14 @@ export function FileImport({ onFileImported, onClose }: FileImportProps) {
     setSuccess(false);
 
     try {
-      const files = await open({
-        multiple: false,
-        filters: [
-          {
-            name: "Documents",
-            extensions: [
-              "txt",
-              "md",
-              "json",
-              "csv",
-              "log",
-              "js",
-              "ts",
-              "tsx",
-              "jsx",
-              "py",
-              "html",
-              "css",
-              "xml",
-            ],
-          },
-          { name: "All Files", extensions: ["*"] },
-        ],
-      });
+      // Picking through the backend grants read_file_content access to the file
+      const filePath = await invoke<string | null>("pick_file_to_read");
 
-      if (!files) {
+      if (!filePath) {
         setLoading(false);
         return;
       }
 
-      const filePath = Array.isArray(files) ? files[0] : files;
       const fileName = filePath.split(/[\\/]/).pop() || "file";
 
       // Read file content using Tauri command
@@ -74,7 +51,7 @@ export function FileImport({ onFileImported, onClose }: FileImportProps) {
       setSuccess(true);
       setTimeout(() => onClose(), 1500);
     } catch (e: unknown) {
-      setError(String(e));
+      setError(formatError(e));
     } finally {
       setLoading(false);
     }
@@ -104,7 +81,7 @@ export function FileImport({ onFileImported, onClose }: FileImportProps) {
       setSuccess(true);
       setTimeout(() => onClose(), 1500);
     } catch (e: unknown) {
-      setError(`Failed to fetch URL: ${String(e)}`);
+      setError(`Failed to fetch URL: ${formatError(e)}`);
     } finally {
       setLoading(false);
     }
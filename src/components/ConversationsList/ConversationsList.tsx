@@ -9,6 +9,7 @@ import {
   Clock,
   MessageSquare,
 } from "lucide-react";
+import { formatError } from "../../utils/errors";
 
 type Conversation = {
   id: number;
@@ -47,7 +48,7 @@ export function ConversationsList({ onNavigate }: ConversationsListProps) {
       setLoading(false);
     } catch (err) {
       console.error("Failed to load conversations:", err);
-      setError(String(err));
+      setError(formatError(err));
       setLoading(false);
     }
   }
@@ -66,7 +67,7 @@ export function ConversationsList({ onNavigate }: ConversationsListProps) {
       setConversations((prev) => prev.filter((c) => c.id !== id));
     } catch (err) {
       console.error("Failed to delete conversation:", err);
-      setError(String(err));
+      setError(formatError(err));
     }
   };
 
@@ -31,6 +31,7 @@ import type {
   AnalysisFormatType,
   PresetMeta,
 } from "./types";
+import { formatError } from "../../utils/errors";
 
 type DownloadState = {
   filename: string;
@@ -192,7 +193,7 @@ export function NewConversation({ onNavigate }: Props) {
       setInstalledPresets((prev) => new Set([...prev, selectedPreset]));
       setDownloadStatus("done");
     } catch (e) {
-      setError(String(e));
+      setError(formatError(e));
     } finally {
       setBusy(false);
     }
@@ -232,7 +233,7 @@ export function NewConversation({ onNavigate }: Props) {
     } catch (e) {
       setBusy(false);
       setDownloadStatus("idle");
-      setError(String(e));
+      setError(formatError(e));
     }
   }
 
@@ -416,7 +417,7 @@ export function NewConversation({ onNavigate }: Props) {
       onNavigate("chat", String(conversationId));
     } catch (e) {
       setBusy(false);
-      setError(String(e));
+      setError(formatError(e));
     }
   }
 
@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { getVersion } from "@tauri-apps/api/app";
 import { Download, CheckCircle, AlertCircle, RefreshCw } from "lucide-react";
 import { i18n } from "../../i18n";
+import { formatError } from "../../utils/errors";
 
 export default function UpdateSection() {
   const [checking, setChecking] = useState(false);
@@ -82,7 +83,7 @@ export default function UpdateSection() {
         setUpdateAvailable(true);
       }
     } catch (err) {
-      setError((err as string) || t.error || "Update check failed");
+      setError(formatError(err) || t.error || "Update check failed");
     } finally {
       setChecking(false);
     }
@@ -96,7 +97,7 @@ export default function UpdateSection() {
       await invoke("install_update");
       // Update will trigger restart automatically
     } catch (err) {
-      setError((err as string) || "Update installation failed");
+      setError(formatError(err) || "Update installation failed");
       setDownloading(false);
     }
   };
@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { getVersion } from "@tauri-apps/api/app";
 import { X, Download, AlertCircle } from "lucide-react";
 import { i18n } from "../i18n";
+import { formatError } from "../utils/errors";
 
 export default function UpdateNotification() {
   const [updateAvailable, setUpdateAvailable] = useState(false);
@@ -76,7 +77,7 @@ export default function UpdateNotification() {
       await invoke("install_update");
       // Update will trigger restart automatically
     } catch (err) {
-      setError(err as string);
+      setError(formatError(err));
       setDownloading(false);
     }
   };
@@ -2,6 +2,7 @@ import type { ReactNode } from "react";
 import { createContext, useContext, useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { formatError } from "../utils/errors";
 
 type ServerStatus = "checking" | "starting" | "ready" | "stopped" | "error";
 
@@ -136,7 +137,7 @@ export function ServerProvider({ children }: { children: ReactNode }) {
         } catch (err) {
           console.error("[ServerContext] Failed to start by preset:", err);
           setStatus("error");
-          setError(`Failed to start by preset: ${String(err)}`);
+          setError(`Failed to start by preset: ${formatError(err)}`);
           setIsStarting(false);
           return;
         }
@@ -160,7 +161,7 @@ export function ServerProvider({ children }: { children: ReactNode }) {
     } catch (err) {
       console.error("[ServerContext] FATAL ERROR in startServer:", err);
       setStatus("error");
-      setError(`Fatal error: ${String(err)}`);
+      setError(`Fatal error: ${formatError(err)}`);
     } finally {
       setIsStarting(false);
       console.log("[ServerContext] ====== START SERVER COMPLETE ======");
@@ -246,7 +247,7 @@ export function ServerProvider({ children }: { children: ReactNode }) {
         err
       );
       setStatus("error");
-      setError(`Fatal error: ${String(err)}`);
+      setError(`Fatal error: ${formatError(err)}`);
     } finally {
       setIsStarting(false);
       console.log(
@@ -265,7 +266,7 @@ export function ServerProvider({ children }: { children: ReactNode }) {
       console.log("[ServerContext] ====== SERVER STOPPED ======");
     } catch (err) {
       console.error("[ServerContext] Failed to stop server:", err);
-      setError(`Failed to stop: ${String(err)}`);
+      setError(`Failed to stop: ${formatError(err)}`);
     }
   };
 
@@ -348,7 +349,7 @@ export function ServerProvider({ children }: { children: ReactNode }) {
       } catch (err) {
         console.error("[ServerContext] FATAL ERROR during init:", err);
         setStatus("error");
-        setError(`Init error: ${String(err)}`);
+        setError(`Init error: ${formatError(err)}`);
       }
       console.log("[ServerContext] ====== INITIALIZATION COMPLETE ======");
     })();
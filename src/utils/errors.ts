@@ -27,3 +27,13 @@ export function logError(context: string, error: unknown): void {
   console.error(`[${context}] Error:`, error);
   console.error(`[${context}] Formatted:`, formatError(error));
 }
+
+/**
+ * Machine-readable code of a backend command error ({ code, message }), if any
+ */
+export function errorCode(error: unknown): string | null {
+  if (error && typeof error === "object" && "code" in error) {
+    return String(error.code);
+  }
+  return null;
+}